@@ -1,8 +1,15 @@
-use std::{env, fs::File, io::Write, mem, path::Path};
+use std::{
+    env,
+    fs::{self, File},
+    io::Write,
+    mem,
+    path::Path,
+};
 
 use tablegen::{
     king_move::generate_king_attacks,
     knight_move::generate_knight_attacks,
+    kpk::{generate_kpk_bitbase, KPK_BITBASE_SIZE},
     magics::{BISHOP_MAGICS, ROOK_MAGICS, SLIDER_ATTACK_TABLE_SIZE},
     pawn_move::generate_pawn_attacks,
     rays_between::generate_squares_between,
@@ -24,6 +31,36 @@ fn write_slice_to_file<T>(file: impl AsRef<Path>, table: T) {
     }
 }
 
+/// Embeds the network given by `EVALFILE`, or an all zero network in the format of
+/// `evaluation/nnue.rs` if none is given
+fn write_default_network() {
+    const FEATURES: u32 = 768;
+    const HIDDEN: u32 = 128;
+    const QA: u32 = 255;
+    const QB: u32 = 64;
+    const SCALE: u32 = 400;
+
+    println!("cargo:rerun-if-env-changed=EVALFILE");
+
+    let path = Path::new(&env::var_os("OUT_DIR").unwrap()).join("default.nnue");
+
+    if let Some(eval_file) = env::var_os("EVALFILE") {
+        println!("cargo:rerun-if-changed={}", eval_file.to_string_lossy());
+        fs::copy(&eval_file, &path).expect("EVALFILE can be read");
+        return;
+    }
+
+    let mut bytes = b"SNUE".to_vec();
+    for value in [1, FEATURES, HIDDEN, QA, QB, SCALE] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let num_parameters = FEATURES * HIDDEN + HIDDEN + 2 * HIDDEN + 1;
+    bytes.resize(bytes.len() + 2 * num_parameters as usize, 0);
+
+    fs::write(path, bytes).unwrap();
+}
+
 fn main() {
     println!("cargo:rerun-if-changed=../tablegen/");
 
@@ -34,6 +71,7 @@ fn main() {
     let knight_attacks: [BitBoard; 64] = generate_knight_attacks();
     let slider_attacks: [BitBoard; SLIDER_ATTACK_TABLE_SIZE] = generate_slider_attacks();
     let zobrist: GeneratedKeys = generate_keys();
+    let kpk_bitbase: [u64; KPK_BITBASE_SIZE] = generate_kpk_bitbase();
 
     write_slice_to_file("pawn_attacks", pawn_attacks);
     write_slice_to_file("king_attacks", king_attacks);
@@ -44,4 +82,7 @@ fn main() {
     write_slice_to_file("squares_between", squares_between);
     write_slice_to_file("squares_line", squares_line);
     write_slice_to_file("zobrist", zobrist);
+    write_slice_to_file("kpk_bitbase", kpk_bitbase);
+
+    write_default_network();
 }
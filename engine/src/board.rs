@@ -1,13 +1,16 @@
+use crate::evaluation::hce::{phase_weight, psq, raw_piece_value};
+use crate::evaluation::score::Score;
 use crate::movegen::attacks::{
-    between, get_bishop_attacks, get_knight_attacks, get_pawn_attacks, get_rook_attacks,
+    between, get_bishop_attacks, get_king_attacks, get_knight_attacks, get_pawn_attacks,
+    get_rook_attacks,
 };
-use crate::movegen::{generate_moves, MoveList};
+use crate::movegen::{self, generate_moves, generate_quiet_moves, MoveList};
 use crate::types::bitboard::BitBoard;
 use crate::types::castling_rights::{CastlingRights, UPDATE_CASTLING_RIGHT_TABLE};
 use crate::types::chess_move::MoveFlag::{Castling, DoublePawnPush, EnPassant};
 use crate::types::chess_move::{Move, MoveFlag};
 use crate::types::color::{Color, PerColor};
-use crate::types::piece::{PerPieceType, Piece, PieceType};
+use crate::types::piece::{PerPieceType, Piece, PieceType, ALL_PIECES};
 use crate::types::square::{File, PerSquare, Square};
 use crate::zobrist::{CASTLE_KEYS, EN_PASSANT_KEYS, PIECE_KEYS, SIDE_KEY};
 use std::fmt;
@@ -17,13 +20,18 @@ use std::str::FromStr;
 #[derive(Debug, Clone)]
 pub struct BoardState {
     hash: u64,
+    /// Zobrist key of the pawns only, used to cache the pawn structure evaluation
+    pawn_key: u64,
     en_passant_target: Option<Square>,
     castling_rights: CastlingRights,
     rule50: u8,
+    plies_from_null: u8,
     checkers: BitBoard,
     pinned: BitBoard,
     last_move: Option<Move>,
     captured_piece: Option<Piece>,
+    psq: Score,
+    phase: u8,
 }
 
 #[derive(Debug, Clone)]
@@ -54,7 +62,8 @@ impl Board {
         new_state.last_move = Some(mov);
 
         self.game_ply += 1;
-        new_state.rule50 += 1;
+        new_state.rule50 = new_state.rule50.saturating_add(1);
+        new_state.plies_from_null = new_state.plies_from_null.saturating_add(1);
 
         // en passant is cleared after doing any move
         new_state.en_passant_target = None;
@@ -74,6 +83,7 @@ impl Board {
         self.occupancies[self.side_to_move] ^= mov.from();
         self.combined ^= mov.from();
         new_state.hash ^= PIECE_KEYS[self.side_to_move][source_piece.ty()][mov.from()];
+        new_state.psq -= psq(self.side_to_move, source_piece.ty(), mov.from());
 
         self.mailbox[mov.from()] = None;
 
@@ -82,6 +92,12 @@ impl Board {
         self.occupancies[self.side_to_move] |= mov.to();
         self.combined |= mov.to();
         new_state.hash ^= PIECE_KEYS[self.side_to_move][source_piece.ty()][mov.to()];
+        new_state.psq += psq(self.side_to_move, source_piece.ty(), mov.to());
+
+        if source_piece.ty() == PieceType::Pawn {
+            new_state.pawn_key ^= PIECE_KEYS[self.side_to_move][PieceType::Pawn][mov.from()];
+            new_state.pawn_key ^= PIECE_KEYS[self.side_to_move][PieceType::Pawn][mov.to()];
+        }
 
         self.mailbox[mov.to()] = Some(source_piece);
 
@@ -98,6 +114,12 @@ impl Board {
             }
             self.occupancies[!self.side_to_move] ^= mov.to();
             new_state.hash ^= PIECE_KEYS[!self.side_to_move][target_piece.ty()][mov.to()];
+            new_state.psq -= psq(!self.side_to_move, target_piece.ty(), mov.to());
+            new_state.phase -= phase_weight(target_piece.ty());
+
+            if target_piece.ty() == PieceType::Pawn {
+                new_state.pawn_key ^= PIECE_KEYS[!self.side_to_move][PieceType::Pawn][mov.to()];
+            }
 
             // combined is unchanged here
 
@@ -114,6 +136,7 @@ impl Board {
             }
 
             new_state.rule50 = 0;
+            new_state.plies_from_null = 0;
         }
 
         if let Some(promotion) = mov.promotion() {
@@ -126,6 +149,10 @@ impl Board {
 
             new_state.hash ^= PIECE_KEYS[self.side_to_move][source_piece.ty()][mov.to()];
             new_state.hash ^= PIECE_KEYS[self.side_to_move][promotion.as_piece_type()][mov.to()];
+            new_state.psq -= psq(self.side_to_move, source_piece.ty(), mov.to());
+            new_state.psq += psq(self.side_to_move, promotion.as_piece_type(), mov.to());
+            new_state.phase += phase_weight(promotion.as_piece_type());
+            new_state.pawn_key ^= PIECE_KEYS[self.side_to_move][PieceType::Pawn][mov.to()];
         }
 
         if mov.flag() == DoublePawnPush {
@@ -144,6 +171,8 @@ impl Board {
             self.mailbox[capture_piece] = None;
 
             new_state.hash ^= PIECE_KEYS[!self.side_to_move][PieceType::Pawn][capture_piece];
+            new_state.psq -= psq(!self.side_to_move, PieceType::Pawn, capture_piece);
+            new_state.pawn_key ^= PIECE_KEYS[!self.side_to_move][PieceType::Pawn][capture_piece];
         }
 
         const CASTLE_CONFIG: [(File, File); 2] = [(File::A, File::D), (File::H, File::F)];
@@ -162,6 +191,7 @@ impl Board {
             self.occupancies[self.side_to_move] ^= rook_start_square;
             self.combined ^= rook_start_square;
             new_state.hash ^= PIECE_KEYS[self.side_to_move][PieceType::Rook][rook_start_square];
+            new_state.psq -= psq(self.side_to_move, PieceType::Rook, rook_start_square);
 
             self.mailbox[rook_start_square] = None;
 
@@ -170,12 +200,14 @@ impl Board {
             self.occupancies[self.side_to_move] |= rook_end_square;
             self.combined |= rook_end_square;
             new_state.hash ^= PIECE_KEYS[self.side_to_move][PieceType::Rook][rook_end_square];
+            new_state.psq += psq(self.side_to_move, PieceType::Rook, rook_end_square);
 
             self.mailbox[rook_end_square] = Some(PieceType::Rook.to_piece(self.side_to_move));
         }
 
         if source_piece.ty() == PieceType::Pawn {
             new_state.rule50 = 0;
+            new_state.plies_from_null = 0;
         }
 
         // update castling rights
@@ -199,43 +231,7 @@ impl Board {
         self.side_to_move = !self.side_to_move;
         new_state.hash ^= SIDE_KEY;
 
-        let king_square =
-            (self.pieces(PieceType::King) & self.occupancies(self.side_to_move())).bit_scan();
-
-        let mut potential_pinners = BitBoard(0);
-        let mut pinned = BitBoard(0);
-
-        let mut checkers = BitBoard(0);
-
-        // pretend king is a bishop and see if any other bishop OR queen is attacked by that
-        potential_pinners |= get_bishop_attacks(king_square, BitBoard(0))
-            & (self.pieces(PieceType::Bishop) | self.pieces(PieceType::Queen));
-
-        // now pretend the king is a rook and so the same procedure
-        potential_pinners |= get_rook_attacks(king_square, BitBoard(0))
-            & (self.pieces(PieceType::Rook) | self.pieces(PieceType::Queen));
-
-        // limit to opponent's pieces
-        potential_pinners &= self.occupancies(!self.side_to_move());
-
-        for square in potential_pinners.iter() {
-            let potentially_pinned = between(square, king_square) & self.combined();
-            if potentially_pinned.is_empty() {
-                checkers |= square;
-            } else if potentially_pinned.count() == 1 {
-                pinned |= potentially_pinned;
-            }
-        }
-
-        // now pretend the king is a knight and check if it attacks an enemy knight
-        checkers |= get_knight_attacks(king_square)
-            & self.pieces(PieceType::Knight)
-            & self.occupancies(!self.side_to_move());
-
-        // do the same thing for pawns
-        checkers |= get_pawn_attacks(king_square, self.side_to_move())
-            & self.pieces(PieceType::Pawn)
-            & self.occupancies(!self.side_to_move());
+        let (checkers, pinned) = self.compute_checkers_and_pinned();
 
         // update pinned, checkers
         new_state.pinned = pinned;
@@ -323,6 +319,84 @@ impl Board {
         }
     }
 
+    /// Passes the turn to the opponent without moving a piece. It is the callers responsibility
+    /// to ensure the side to move is not in check.
+    pub fn apply_null_move(&mut self) {
+        let mut new_state = self.state.clone();
+
+        new_state.last_move = None;
+        new_state.captured_piece = None;
+
+        new_state.rule50 = new_state.rule50.saturating_add(1);
+        new_state.plies_from_null = 0;
+
+        if let Some(en_passant_target) = new_state.en_passant_target.take() {
+            new_state.hash ^= EN_PASSANT_KEYS[en_passant_target.to_file()];
+        }
+
+        self.side_to_move = !self.side_to_move;
+        new_state.hash ^= SIDE_KEY;
+
+        let (checkers, pinned) = self.compute_checkers_and_pinned();
+        new_state.pinned = pinned;
+        new_state.checkers = checkers;
+
+        let old_state = std::mem::replace(&mut self.state, new_state);
+        self.history.push(old_state);
+    }
+
+    pub fn undo_null_move(&mut self) {
+        self.side_to_move = !self.side_to_move;
+
+        if let Some(previous_state) = self.history.pop() {
+            self.state = previous_state;
+        }
+    }
+
+    /// Computes the opponent pieces giving check and the own pieces pinned to the king for the
+    /// side to move.
+    fn compute_checkers_and_pinned(&self) -> (BitBoard, BitBoard) {
+        let king_square =
+            (self.pieces(PieceType::King) & self.occupancies(self.side_to_move())).bit_scan();
+
+        let mut potential_pinners = BitBoard(0);
+        let mut pinned = BitBoard(0);
+
+        let mut checkers = BitBoard(0);
+
+        // pretend king is a bishop and see if any other bishop OR queen is attacked by that
+        potential_pinners |= get_bishop_attacks(king_square, BitBoard(0))
+            & (self.pieces(PieceType::Bishop) | self.pieces(PieceType::Queen));
+
+        // now pretend the king is a rook and so the same procedure
+        potential_pinners |= get_rook_attacks(king_square, BitBoard(0))
+            & (self.pieces(PieceType::Rook) | self.pieces(PieceType::Queen));
+
+        // limit to opponent's pieces
+        potential_pinners &= self.occupancies(!self.side_to_move());
+
+        for square in potential_pinners.iter() {
+            let potentially_pinned = between(square, king_square) & self.combined();
+            if potentially_pinned.is_empty() {
+                checkers |= square;
+            } else if potentially_pinned.count() == 1 {
+                pinned |= potentially_pinned;
+            }
+        }
+
+        // now pretend the king is a knight and check if it attacks an enemy knight
+        checkers |= get_knight_attacks(king_square)
+            & self.pieces(PieceType::Knight)
+            & self.occupancies(!self.side_to_move());
+
+        // do the same thing for pawns
+        checkers |= get_pawn_attacks(king_square, self.side_to_move())
+            & self.pieces(PieceType::Pawn)
+            & self.occupancies(!self.side_to_move());
+
+        (checkers, pinned)
+    }
+
     pub fn generate_moves(&self) -> MoveList {
         generate_moves::<false>(self)
     }
@@ -331,11 +405,27 @@ impl Board {
         generate_moves::<true>(self)
     }
 
+    pub fn generate_moves_quiets_only(&self) -> MoveList {
+        generate_quiet_moves(self)
+    }
+
+    /// Checks whether a move that was not generated in this position can be played, see
+    /// [`movegen::is_pseudo_legal`]
+    pub fn is_pseudo_legal(&self, mov: Move) -> bool {
+        movegen::is_pseudo_legal(self, mov)
+    }
+
+    /// Checks whether a move that was not generated in this position is legal, see
+    /// [`movegen::is_legal`]
+    pub fn is_legal(&self, mov: Move) -> bool {
+        movegen::is_legal(self, mov)
+    }
+
     pub fn is_repetition(&self) -> bool {
         self.history
             .iter()
             .rev()
-            .take(self.state.rule50 as usize)
+            .take(self.state.rule50.min(self.state.plies_from_null) as usize)
             .filter(|c| self.state.hash == c.hash)
             .count()
             >= 1
@@ -373,6 +463,130 @@ impl Board {
         self.state.pinned
     }
 
+    pub fn last_move(&self) -> Option<Move> {
+        self.state.last_move
+    }
+
+    /// Returns true if the given side has any pieces besides pawns and the king
+    pub fn has_non_pawn_material(&self, color: Color) -> bool {
+        let pawns_and_king = self.pieces(PieceType::Pawn) | self.pieces(PieceType::King);
+        !(self.occupancies(color) & !pawns_and_king).is_empty()
+    }
+
+    /// Returns the pieces of both colors attacking `square`. Only pieces in `occupancy` attack
+    /// and block, so removing a piece from it reveals the sliders behind it.
+    pub fn attackers_to(&self, square: Square, occupancy: BitBoard) -> BitBoard {
+        let bishops = self.pieces(PieceType::Bishop) | self.pieces(PieceType::Queen);
+        let rooks = self.pieces(PieceType::Rook) | self.pieces(PieceType::Queen);
+
+        let attackers = (get_pawn_attacks(square, Color::White)
+            & self.pieces(PieceType::Pawn)
+            & self.occupancies(Color::Black))
+            | (get_pawn_attacks(square, Color::Black)
+                & self.pieces(PieceType::Pawn)
+                & self.occupancies(Color::White))
+            | (get_knight_attacks(square) & self.pieces(PieceType::Knight))
+            | (get_king_attacks(square) & self.pieces(PieceType::King))
+            | (get_bishop_attacks(square, occupancy) & bishops)
+            | (get_rook_attacks(square, occupancy) & rooks);
+
+        attackers & occupancy
+    }
+
+    /// Static exchange evaluation. Checks whether the sequence of captures on the target square
+    /// of `mov` wins at least `threshold` centipawns for the side to move, assuming both sides
+    /// always recapture with their least valuable piece and may stop capturing at any point.
+    pub fn see(&self, mov: Move, threshold: i16) -> bool {
+        if mov.flag() == Castling {
+            return threshold <= 0;
+        }
+
+        let from = mov.from();
+        let to = mov.to();
+
+        let mut occupancy = self.combined() ^ BitBoard::from_square(from);
+
+        let captured = if mov.flag() == EnPassant {
+            let captured_square = to.forward(!self.side_to_move()).unwrap();
+            occupancy ^= BitBoard::from_square(captured_square);
+            Some(PieceType::Pawn)
+        } else {
+            self.piece_at(to).map(|piece| piece.ty())
+        };
+
+        // the piece standing on the target square after the move, which is the next victim
+        let mut victim = self.piece_at(from).unwrap().ty();
+        let mut balance = captured.map_or(0, raw_piece_value) - threshold;
+
+        if let Some(promotion) = mov.promotion() {
+            victim = promotion.as_piece_type();
+            balance += raw_piece_value(victim) - raw_piece_value(PieceType::Pawn);
+        }
+
+        // even winning the capture for free does not reach the threshold
+        if balance < 0 {
+            return false;
+        }
+
+        // losing the moved piece still reaches the threshold
+        balance -= raw_piece_value(victim);
+        if balance >= 0 {
+            return true;
+        }
+
+        occupancy |= BitBoard::from_square(to);
+
+        let bishops = self.pieces(PieceType::Bishop) | self.pieces(PieceType::Queen);
+        let rooks = self.pieces(PieceType::Rook) | self.pieces(PieceType::Queen);
+
+        let mut attackers = self.attackers_to(to, occupancy);
+        let mut color = !self.side_to_move();
+
+        loop {
+            let own_attackers = attackers & self.occupancies(color);
+            if own_attackers.is_empty() {
+                break;
+            }
+
+            let attacker = ALL_PIECES
+                .into_iter()
+                .find(|&piece| !(own_attackers & self.pieces(piece)).is_empty())
+                .unwrap();
+
+            color = !color;
+
+            // negamax the balance: the side that just captured is now the one to lose a piece
+            balance = -balance - 1 - raw_piece_value(attacker);
+
+            if balance >= 0 {
+                // the king may only recapture if the square is not defended anymore
+                if attacker == PieceType::King && !(attackers & self.occupancies(color)).is_empty()
+                {
+                    color = !color;
+                }
+                break;
+            }
+
+            occupancy ^= BitBoard::from_square((own_attackers & self.pieces(attacker)).bit_scan());
+
+            // reveal the sliders behind the piece that just captured
+            if matches!(
+                attacker,
+                PieceType::Pawn | PieceType::Bishop | PieceType::Queen
+            ) {
+                attackers |= get_bishop_attacks(to, occupancy) & bishops;
+            }
+            if matches!(attacker, PieceType::Rook | PieceType::Queen) {
+                attackers |= get_rook_attacks(to, occupancy) & rooks;
+            }
+
+            attackers &= occupancy;
+        }
+
+        // the side that is to move in the exchange when it ends lost it
+        color != self.side_to_move()
+    }
+
     pub fn castling_rights(&self) -> CastlingRights {
         self.state.castling_rights
     }
@@ -389,6 +603,85 @@ impl Board {
     pub fn hash(&self) -> u64 {
         self.state.hash
     }
+
+    #[inline]
+    pub fn pawn_key(&self) -> u64 {
+        self.state.pawn_key
+    }
+
+    /// Sum of the material and piece square values of all pieces from white's point of view
+    #[inline]
+    pub fn psq(&self) -> Score {
+        self.state.psq
+    }
+
+    /// Game phase derived from the remaining minor and major pieces, see [`phase_weight`]
+    #[inline]
+    pub fn phase(&self) -> u8 {
+        self.state.phase
+    }
+
+    /// Returns the hash of the position after `mov` without applying it, which allows
+    /// prefetching the transposition table entry of the child position early. The move is
+    /// assumed to be legal.
+    pub fn key_after(&self, mov: Move) -> u64 {
+        let us = self.side_to_move;
+        let source_piece = self.piece_at(mov.from()).unwrap().ty();
+
+        let mut hash = self.state.hash ^ SIDE_KEY;
+
+        if let Some(en_passant_target) = self.en_passant_target() {
+            hash ^= EN_PASSANT_KEYS[en_passant_target.to_file()];
+        }
+
+        let placed_piece = mov.promotion().map_or(source_piece, |p| p.as_piece_type());
+        hash ^= PIECE_KEYS[us][source_piece][mov.from()];
+        hash ^= PIECE_KEYS[us][placed_piece][mov.to()];
+
+        let mut castling_rights = self.state.castling_rights;
+
+        match mov.flag() {
+            EnPassant => {
+                let capture_square = mov.to().forward(!us).unwrap();
+                hash ^= PIECE_KEYS[!us][PieceType::Pawn][capture_square];
+            }
+            DoublePawnPush => {
+                hash ^= EN_PASSANT_KEYS[mov.to().to_file()];
+            }
+            Castling => {
+                const CASTLE_CONFIG: [(File, File); 2] = [(File::A, File::D), (File::H, File::F)];
+
+                let backrank = us.backrank();
+                let (rook_start_file, rook_end_file) =
+                    CASTLE_CONFIG[mov.to().to_file() as usize / 4];
+
+                hash ^= PIECE_KEYS[us][PieceType::Rook][Square::from(backrank, rook_start_file)];
+                hash ^= PIECE_KEYS[us][PieceType::Rook][Square::from(backrank, rook_end_file)];
+            }
+            _ if mov.is_capture() => {
+                let target_piece = self.piece_at(mov.to()).unwrap().ty();
+                hash ^= PIECE_KEYS[!us][target_piece][mov.to()];
+
+                if target_piece == PieceType::Rook {
+                    castling_rights &= UPDATE_CASTLING_RIGHT_TABLE[mov.from()];
+                    castling_rights &= UPDATE_CASTLING_RIGHT_TABLE[mov.to()];
+                }
+            }
+            _ => {}
+        }
+
+        if source_piece == PieceType::Rook {
+            castling_rights &= UPDATE_CASTLING_RIGHT_TABLE[mov.from()];
+            castling_rights &= UPDATE_CASTLING_RIGHT_TABLE[mov.to()];
+        } else if source_piece == PieceType::King {
+            castling_rights -= match us {
+                Color::White => CastlingRights::WHITE_BOTH_SIDES,
+                Color::Black => CastlingRights::BLACK_BOTH_SIDES,
+            };
+        }
+
+        hash ^ CASTLE_KEYS[self.state.castling_rights] ^ CASTLE_KEYS[castling_rights]
+    }
 }
 
 impl fmt::Display for Board {
@@ -450,6 +743,9 @@ impl FromStr for Board {
         let mut mailbox = PerSquare::<Option<Piece>>::default();
 
         let mut hash = 0;
+        let mut pawn_key = 0;
+        let mut psq_score = Score::ZERO;
+        let mut phase = 0;
 
         let piece_placement_data = parts
             .next()
@@ -475,6 +771,11 @@ impl FromStr for Board {
                     mailbox[square] = Some(piece);
 
                     hash ^= PIECE_KEYS[color][piece_type][square];
+                    psq_score += psq(color, piece_type, square);
+                    if piece_type == PieceType::Pawn {
+                        pawn_key ^= PIECE_KEYS[color][piece_type][square];
+                    }
+                    phase += phase_weight(piece_type);
 
                     file += 1;
                     if file > 8 {
@@ -599,13 +900,17 @@ impl FromStr for Board {
             mailbox,
             state: BoardState {
                 hash,
+                pawn_key,
                 en_passant_target,
                 castling_rights,
                 rule50: halfmove_clock,
+                plies_from_null: 0,
                 checkers,
                 pinned,
                 last_move: None,
                 captured_piece: None,
+                psq: psq_score,
+                phase,
             },
             history: vec![],
             game_ply: (2 * (fullmove_number - 1)).max(0) + [0, 1][side_to_move as usize],
@@ -639,6 +944,14 @@ mod test {
     use std::str::FromStr;
 
     use crate::board::Board;
+    use crate::evaluation::hce::{phase_weight, psq};
+    use crate::evaluation::score::Score;
+    use crate::types::bitboard::BitBoard;
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::color::Color;
+    use crate::types::piece::PieceType;
+    use crate::types::square::Square;
+    use crate::zobrist::PIECE_KEYS;
 
     #[test]
     fn test_display() {
@@ -697,4 +1010,243 @@ Hash: 	0x4a887e3c9bc2624a
 
         println!("{board}");
     }
+
+    #[test]
+    fn test_null_move() {
+        let mut board =
+            Board::from_str("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2").unwrap();
+        board.apply_move(Move::new(Square::F7, Square::F5, MoveFlag::DoublePawnPush));
+
+        let before = board.clone();
+        let hash_before = board.hash();
+
+        board.apply_null_move();
+
+        // passing the turn is the same as the position with the other side to move and no en
+        // passant target
+        let expected =
+            Board::from_str("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3")
+                .unwrap();
+        assert_eq!(board, expected);
+        assert_eq!(board.hash(), expected.hash());
+        assert_eq!(board.en_passant_target(), None);
+        assert!(board.last_move().is_none());
+
+        board.undo_null_move();
+
+        assert_eq!(board, before);
+        assert_eq!(board.hash(), hash_before);
+        assert_eq!(board.en_passant_target(), Some(Square::F6));
+    }
+
+    #[test]
+    fn test_repetition_in_long_game() {
+        let mut board = Board::default();
+        let shuffle = [
+            Move::new(Square::G1, Square::F3, MoveFlag::Normal),
+            Move::new(Square::G8, Square::F6, MoveFlag::Normal),
+            Move::new(Square::F3, Square::G1, MoveFlag::Normal),
+            Move::new(Square::F6, Square::G8, MoveFlag::Normal),
+        ];
+
+        // more plies than fit the counters without a capture or pawn move
+        for mov in shuffle.iter().cycle().take(300) {
+            board.apply_move(*mov);
+        }
+        assert!(board.is_repetition());
+
+        board.apply_null_move();
+        assert!(!board.is_repetition());
+    }
+
+    #[test]
+    fn test_null_move_updates_pins() {
+        // the white bishop pins the black knight to its king after the turn is passed
+        let mut board = Board::from_str("4k3/8/8/1B6/8/8/8/4K3 w - - 0 1").unwrap();
+        board.apply_null_move();
+        assert!(board.pinned().is_empty());
+        board.undo_null_move();
+
+        let mut board = Board::from_str("4k3/3n4/8/1B6/8/8/8/4K3 w - - 0 1").unwrap();
+        board.apply_null_move();
+        assert_eq!(board.pinned(), BitBoard::from_square(Square::D7));
+        assert!(board.checkers().is_empty());
+    }
+
+    #[test]
+    fn test_non_pawn_material() {
+        let board = Board::from_str("4k3/pppp4/8/8/8/8/4P3/2B1K3 w - - 0 1").unwrap();
+        assert!(board.has_non_pawn_material(Color::White));
+        assert!(!board.has_non_pawn_material(Color::Black));
+    }
+
+    #[test]
+    fn test_key_after_matches_applied_move() {
+        let fens = [
+            Board::STARTING_POS_FEN,
+            Board::KILLER_POS_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ];
+
+        for fen in fens {
+            let mut board = Board::from_str(fen).unwrap();
+
+            for mov in board.generate_moves() {
+                let key = board.key_after(mov);
+                board.apply_move(mov);
+                assert_eq!(key, board.hash(), "{fen} {mov}");
+
+                // also cover the replies, which include en passant captures after double pushes
+                for reply in board.generate_moves() {
+                    let key = board.key_after(reply);
+                    board.apply_move(reply);
+                    assert_eq!(key, board.hash(), "{fen} {mov} {reply}");
+                    board.undo_move();
+                }
+
+                board.undo_move();
+            }
+        }
+    }
+
+    #[test]
+    fn test_incremental_evaluation_state() {
+        fn recompute(board: &Board) -> (Score, u8, u64) {
+            let mut state = (Score::ZERO, 0, 0);
+            for square in board.combined().iter() {
+                let piece = board.piece_at(square).unwrap();
+                state.0 += psq(piece.color(), piece.ty(), square);
+                state.1 += phase_weight(piece.ty());
+                if piece.ty() == PieceType::Pawn {
+                    state.2 ^= PIECE_KEYS[piece.color()][PieceType::Pawn][square];
+                }
+            }
+            state
+        }
+
+        fn incremental(board: &Board) -> (Score, u8, u64) {
+            (board.psq(), board.phase(), board.pawn_key())
+        }
+
+        let fens = [
+            Board::STARTING_POS_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+
+        for fen in fens {
+            let mut board = Board::from_str(fen).unwrap();
+            assert_eq!(incremental(&board), recompute(&board));
+
+            for mov in board.generate_moves() {
+                board.apply_move(mov);
+                assert_eq!(incremental(&board), recompute(&board), "{fen} {mov}");
+
+                for reply in board.generate_moves() {
+                    board.apply_move(reply);
+                    assert_eq!(
+                        incremental(&board),
+                        recompute(&board),
+                        "{fen} {mov} {reply}"
+                    );
+                    board.undo_move();
+                }
+
+                board.undo_move();
+            }
+        }
+
+        assert_eq!(Board::default().phase(), 24);
+    }
+
+    #[test]
+    fn test_attackers_to() {
+        let board = Board::from_str("4k3/4r3/8/4p3/3P4/8/4R3/4R1K1 w - - 0 1").unwrap();
+
+        let attackers = board.attackers_to(Square::E5, board.combined());
+        assert_eq!(
+            attackers,
+            BitBoard::from_square(Square::D4)
+                | BitBoard::from_square(Square::E2)
+                | BitBoard::from_square(Square::E7)
+        );
+
+        // removing the front rook reveals the one behind it
+        let occupancy = board.combined() ^ BitBoard::from_square(Square::E2);
+        assert!(board
+            .attackers_to(Square::E5, occupancy)
+            .contains(Square::E1));
+    }
+
+    #[test]
+    fn test_see_simple_capture() {
+        let board = Board::from_str("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1").unwrap();
+        let mov = Move::new(Square::E1, Square::E5, MoveFlag::Capture);
+
+        assert!(board.see(mov, 100));
+        assert!(!board.see(mov, 101));
+    }
+
+    #[test]
+    fn test_see_exchange_sequence() {
+        let board =
+            Board::from_str("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1").unwrap();
+        let mov = Move::new(Square::D3, Square::E5, MoveFlag::Capture);
+
+        // NxP NxN RxN BxR QxB QxQ, white is best off stopping after losing the knight
+        assert!(board.see(mov, -220));
+        assert!(!board.see(mov, -219));
+    }
+
+    #[test]
+    fn test_see_xray() {
+        let mov = Move::new(Square::E2, Square::E5, MoveFlag::Capture);
+
+        let board = Board::from_str("4k3/4r3/8/4p3/8/8/4R3/6K1 w - - 0 1").unwrap();
+        assert!(!board.see(mov, 0));
+
+        // the second rook recaptures through the first one
+        let board = Board::from_str("4k3/4r3/8/4p3/8/8/4R3/4R1K1 w - - 0 1").unwrap();
+        assert!(board.see(mov, 100));
+        assert!(!board.see(mov, 101));
+    }
+
+    #[test]
+    fn test_see_promotion() {
+        let board = Board::from_str("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+
+        let capture = Move::new(Square::B7, Square::A8, MoveFlag::QueenPromotionCapture);
+        assert!(board.see(capture, 1300));
+        assert!(!board.see(capture, 1301));
+
+        // the new queen is taken by the rook
+        let push = Move::new(Square::B7, Square::B8, MoveFlag::QueenPromotion);
+        assert!(board.see(push, -100));
+        assert!(!board.see(push, 0));
+    }
+
+    #[test]
+    fn test_see_en_passant() {
+        let board = Board::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let mov = Move::new(Square::E5, Square::D6, MoveFlag::EnPassant);
+
+        assert!(board.see(mov, 100));
+        assert!(!board.see(mov, 101));
+    }
+
+    #[test]
+    fn test_see_king_recapture() {
+        // the king can only take back if the square is not defended anymore
+        let mov = Move::new(Square::D5, Square::D7, MoveFlag::Capture);
+
+        let board = Board::from_str("4k3/3p4/8/3R4/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(!board.see(mov, 0));
+
+        let board = Board::from_str("4k3/3p4/8/3R4/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert!(board.see(mov, 100));
+    }
 }
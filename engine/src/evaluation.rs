@@ -1,10 +1,17 @@
+pub mod endgame;
 pub mod hce;
+pub mod king_safety;
+pub mod mobility;
 pub mod nnue;
+pub mod params;
+pub mod pawns;
+pub mod score;
+pub mod trace;
 
 use crate::types::color::Color;
 use std::fmt;
 use std::fmt::Formatter;
-use std::ops::Neg;
+use std::ops::{Add, Neg, Sub};
 
 #[derive(PartialEq, Clone, Copy, Debug, PartialOrd, Ord, Eq)]
 pub struct Evaluation(i16);
@@ -68,6 +75,17 @@ impl Evaluation {
     pub const fn val(&self) -> i16 {
         self.0
     }
+
+    /// Converts a centipawn score, clamped such that it can't be mistaken for a mate score
+    pub fn from_centipawns(value: i32) -> Self {
+        let bound = (Evaluation::IMMEDIATE_MATE_SCORE - Evaluation::MAX_MATE_DEPTH) as i32;
+        Evaluation(value.clamp(-bound, bound) as i16)
+    }
+
+    /// Inverse of [`Evaluation::val`]
+    pub const fn from_val(value: i16) -> Self {
+        Evaluation(value)
+    }
 }
 
 impl Neg for Evaluation {
@@ -78,6 +96,26 @@ impl Neg for Evaluation {
     }
 }
 
+/// Saturates at [`Evaluation::MIN`] and [`Evaluation::MAX`]
+impl Add<i16> for Evaluation {
+    type Output = Self;
+
+    fn add(self, rhs: i16) -> Self::Output {
+        let value = (self.0 as i32 + rhs as i32).clamp(Self::MIN.0 as i32, Self::MAX.0 as i32);
+        Evaluation(value as i16)
+    }
+}
+
+/// Saturates at [`Evaluation::MIN`] and [`Evaluation::MAX`]
+impl Sub<i16> for Evaluation {
+    type Output = Self;
+
+    fn sub(self, rhs: i16) -> Self::Output {
+        let value = (self.0 as i32 - rhs as i32).clamp(Self::MIN.0 as i32, Self::MAX.0 as i32);
+        Evaluation(value as i16)
+    }
+}
+
 impl fmt::Display for Evaluation {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         if self.is_mate() {
@@ -160,6 +198,13 @@ mod test {
         assert_eq!(Evaluation::MAX, -Evaluation::MIN);
     }
 
+    #[test]
+    fn test_offset_saturates() {
+        assert_eq!(Evaluation::MAX + 100, Evaluation::MAX);
+        assert_eq!(Evaluation::MIN - 100, Evaluation::MIN);
+        assert_eq!(Evaluation(30) - 50, Evaluation(-20));
+    }
+
     #[test]
     fn test_mate_num_ply() {
         let evaluation = Evaluation(-Evaluation::IMMEDIATE_MATE_SCORE + 50);
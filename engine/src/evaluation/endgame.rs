@@ -0,0 +1,472 @@
+mod kpk;
+
+use crate::board::Board;
+use crate::types::bitboard::BitBoard;
+use crate::types::color::Color;
+use crate::types::piece::PieceType;
+use crate::types::square::Square;
+
+use super::hce::raw_piece_value;
+use super::score::Score;
+
+/// Evaluation of positions that are known to be won, far above any regular evaluation but below
+/// mate scores
+pub const KNOWN_WIN: i32 = 10000;
+
+/// Scale factor of the endgame score that leaves it unchanged
+pub const NORMAL_SCALE: i32 = 64;
+
+const DRAW_SCALE: i32 = 0;
+/// Without pawns, a rook or more against at most a minor piece, e.g. a rook against a knight
+const ROOK_VS_MINOR_SCALE: i32 = 4;
+/// Without pawns, heavier material up by at most a minor piece, e.g. rook and bishop against rook
+const MINOR_UP_SCALE: i32 = 14;
+/// Opposite coloured bishops as the only pieces, often drawn even a pawn or two down
+const PURE_OPPOSITE_BISHOPS_SCALE: i32 = 18;
+/// Opposite coloured bishops with other pieces on the board, which still favour the defender
+const OPPOSITE_BISHOPS_SCALE: i32 = 46;
+
+/// Number of pieces of each type and color except kings, four bits each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaterialKey(u64);
+
+const KEY_PIECES: [PieceType; 5] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+];
+
+impl MaterialKey {
+    pub fn new(board: &Board) -> Self {
+        let mut key = 0;
+
+        for color in [Color::White, Color::Black] {
+            for piece in KEY_PIECES {
+                let count = (board.pieces(piece) & board.occupancies(color)).count();
+                key |= (count as u64) << Self::shift(color, piece);
+            }
+        }
+
+        Self(key)
+    }
+
+    /// Key of a material signature like `KBNK`, which lists the pieces of `strong` up to its king
+    /// followed by the pieces of the other side
+    const fn from_code(code: &str, strong: Color) -> Self {
+        let code = code.as_bytes();
+        let mut color = strong;
+        let mut key = 0;
+
+        let mut i = 0;
+        while i < code.len() {
+            let piece = match code[i] {
+                b'K' => None,
+                b'P' => Some(PieceType::Pawn),
+                b'N' => Some(PieceType::Knight),
+                b'B' => Some(PieceType::Bishop),
+                b'R' => Some(PieceType::Rook),
+                b'Q' => Some(PieceType::Queen),
+                _ => panic!("invalid piece in material code"),
+            };
+
+            match piece {
+                Some(piece) => key += 1 << Self::shift(color, piece),
+                None if i > 0 => color = opponent(color),
+                None => {}
+            }
+
+            i += 1;
+        }
+
+        Self(key)
+    }
+
+    const fn shift(color: Color, piece: PieceType) -> u32 {
+        (color as u32 * KEY_PIECES.len() as u32 + piece as u32) * 4
+    }
+
+    pub const fn count(self, color: Color, piece: PieceType) -> i32 {
+        ((self.0 >> Self::shift(color, piece)) & 0xF) as i32
+    }
+
+    /// Value of the knights, bishops, rooks and queens of `color`
+    pub const fn non_pawn_material(self, color: Color) -> i32 {
+        let mut material = 0;
+
+        let mut i = 1;
+        while i < KEY_PIECES.len() {
+            let piece = KEY_PIECES[i];
+            material += self.count(color, piece) * raw_piece_value(piece) as i32;
+            i += 1;
+        }
+
+        material
+    }
+
+    pub const fn material(self, color: Color) -> i32 {
+        self.non_pawn_material(color)
+            + self.count(color, PieceType::Pawn) * raw_piece_value(PieceType::Pawn) as i32
+    }
+
+    /// Whether `color` has nothing but its king
+    const fn is_lone_king(self, color: Color) -> bool {
+        let mask = (1 << (KEY_PIECES.len() * 4)) - 1;
+        (self.0 >> Self::shift(color, PieceType::Pawn)) & mask == 0
+    }
+}
+
+const fn opponent(color: Color) -> Color {
+    match color {
+        Color::White => Color::Black,
+        Color::Black => Color::White,
+    }
+}
+
+/// Evaluation from the point of view of the strong side
+type EndgameFn = fn(&Board, MaterialKey, Color) -> i32;
+
+#[derive(Clone, Copy)]
+struct Endgame {
+    key: MaterialKey,
+    strong: Color,
+    evaluate: EndgameFn,
+}
+
+const ENDGAME_CODES: [(&str, EndgameFn); 6] = [
+    ("KPK", kpk),
+    ("KBNK", kbnk),
+    ("KK", draw),
+    ("KNK", draw),
+    ("KBK", draw),
+    ("KNNK", draw),
+];
+
+/// The endgames with specialised evaluations, for both sides as the strong side
+static ENDGAMES: [Endgame; 2 * ENDGAME_CODES.len()] = {
+    let mut endgames = [Endgame {
+        key: MaterialKey(0),
+        strong: Color::White,
+        evaluate: draw,
+    }; 2 * ENDGAME_CODES.len()];
+
+    let mut i = 0;
+    while i < ENDGAME_CODES.len() {
+        let (code, evaluate) = ENDGAME_CODES[i];
+        endgames[2 * i] = Endgame {
+            key: MaterialKey::from_code(code, Color::White),
+            strong: Color::White,
+            evaluate,
+        };
+        endgames[2 * i + 1] = Endgame {
+            key: MaterialKey::from_code(code, Color::Black),
+            strong: Color::Black,
+            evaluate,
+        };
+        i += 1;
+    }
+
+    endgames
+};
+
+/// Looks up the endgame by its material signature. Other positions with a lone king against
+/// enough material to mate are evaluated by [`kxk`].
+fn probe(key: MaterialKey) -> Option<(EndgameFn, Color)> {
+    if let Some(endgame) = ENDGAMES.iter().find(|endgame| endgame.key == key) {
+        return Some((endgame.evaluate, endgame.strong));
+    }
+
+    for strong in [Color::White, Color::Black] {
+        if key.is_lone_king(opponent(strong)) && has_mating_material(key, strong) {
+            return Some((kxk, strong));
+        }
+    }
+
+    None
+}
+
+/// Specialised evaluation from white's point of view, if the material is a known endgame. It
+/// replaces the regular evaluation.
+pub fn evaluate(board: &Board, key: MaterialKey) -> Option<i32> {
+    let (evaluate, strong) = probe(key)?;
+    let value = evaluate(board, key, strong);
+
+    Some(match strong {
+        Color::White => value,
+        Color::Black => -value,
+    })
+}
+
+/// Factor between 0 and [`NORMAL_SCALE`] by which the endgame score is scaled, for material that
+/// is harder to win than its value suggests
+pub fn scale_factor(board: &Board, key: MaterialKey, score: Score) -> i32 {
+    let strong = if score.eg() >= 0 {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let weak = !strong;
+
+    let strong_material = key.non_pawn_material(strong);
+    let weak_material = key.non_pawn_material(weak);
+    let bishop = raw_piece_value(PieceType::Bishop) as i32;
+
+    // without pawns, being up a minor piece at most is rarely enough to win
+    if key.count(strong, PieceType::Pawn) == 0 && strong_material - weak_material <= bishop {
+        return if strong_material < raw_piece_value(PieceType::Rook) as i32 {
+            DRAW_SCALE
+        } else if weak_material <= bishop {
+            ROOK_VS_MINOR_SCALE
+        } else {
+            MINOR_UP_SCALE
+        };
+    }
+
+    if strong_material == bishop && key.count(strong, PieceType::Bishop) == 1 {
+        if weak_material == 0 && is_wrong_bishop_draw(board, strong) {
+            return DRAW_SCALE;
+        }
+
+        if weak_material == bishop
+            && key.count(weak, PieceType::Bishop) == 1
+            && has_opposite_bishops(board)
+        {
+            return PURE_OPPOSITE_BISHOPS_SCALE;
+        }
+    }
+
+    if key.count(Color::White, PieceType::Bishop) == 1
+        && key.count(Color::Black, PieceType::Bishop) == 1
+        && has_opposite_bishops(board)
+    {
+        return OPPOSITE_BISHOPS_SCALE;
+    }
+
+    NORMAL_SCALE
+}
+
+fn has_mating_material(key: MaterialKey, color: Color) -> bool {
+    let count = |piece| key.count(color, piece);
+
+    count(PieceType::Queen) > 0
+        || count(PieceType::Rook) > 0
+        || (count(PieceType::Bishop) > 0 && count(PieceType::Knight) > 0)
+        || count(PieceType::Bishop) > 1
+        || count(PieceType::Knight) > 2
+}
+
+fn has_opposite_bishops(board: &Board) -> bool {
+    let bishops = board.pieces(PieceType::Bishop);
+    let white = bishops & board.occupancies(Color::White);
+    let black = bishops & board.occupancies(Color::Black);
+
+    is_light(white.bit_scan()) != is_light(black.bit_scan())
+}
+
+/// Bishop and rook pawns on a single file can't win when the bishop doesn't control the
+/// promotion square and the defending king reaches it
+fn is_wrong_bishop_draw(board: &Board, strong: Color) -> bool {
+    let pawns = board.pieces(PieceType::Pawn) & board.occupancies(strong);
+    let file = BitBoard::mask_file(pawns.bit_scan().file());
+
+    if !(pawns & !file).is_empty()
+        || !(file & (BitBoard::NOT_A_FILE & BitBoard::NOT_H_FILE)).is_empty()
+    {
+        return false;
+    }
+
+    let promotion = (file & BitBoard::mask_rank((!strong).backrank())).bit_scan();
+    let bishop = (board.pieces(PieceType::Bishop) & board.occupancies(strong)).bit_scan();
+
+    is_light(bishop) != is_light(promotion) && distance(king(board, !strong), promotion) <= 1
+}
+
+fn king(board: &Board, color: Color) -> Square {
+    (board.pieces(PieceType::King) & board.occupancies(color)).bit_scan()
+}
+
+const fn is_light(square: Square) -> bool {
+    (square.file() as u8 + square.rank() as u8) % 2 == 1
+}
+
+fn distance(a: Square, b: Square) -> i32 {
+    let files = (a.file() as i32 - b.file() as i32).abs();
+    let ranks = (a.rank() as i32 - b.rank() as i32).abs();
+
+    files.max(ranks)
+}
+
+/// Bonus for driving the king to the edge of the board
+fn push_to_edge(square: Square) -> i32 {
+    let from_center = |coordinate: i32| (3 - coordinate).max(coordinate - 4);
+    20 * (from_center(square.file() as i32) + from_center(square.rank() as i32))
+}
+
+/// Bonus for bringing the kings close to each other
+fn push_close(a: Square, b: Square) -> i32 {
+    10 * (7 - distance(a, b))
+}
+
+fn draw(_board: &Board, _key: MaterialKey, _strong: Color) -> i32 {
+    0
+}
+
+/// A lone king against enough material to mate: drive the king to the edge and approach it with
+/// the own king
+fn kxk(board: &Board, key: MaterialKey, strong: Color) -> i32 {
+    let (king, weak_king) = (king(board, strong), king(board, !strong));
+
+    KNOWN_WIN + key.material(strong) + push_to_edge(weak_king) + push_close(king, weak_king)
+}
+
+/// Mate with bishop and knight is only possible in a corner of the bishop's color
+fn kbnk(board: &Board, key: MaterialKey, strong: Color) -> i32 {
+    let (king, weak_king) = (king(board, strong), king(board, !strong));
+    let bishop = board.pieces(PieceType::Bishop).bit_scan();
+
+    let corners = if is_light(bishop) {
+        [Square::H1, Square::A8]
+    } else {
+        [Square::A1, Square::H8]
+    };
+    let corner_distance = corners
+        .into_iter()
+        .map(|corner| distance(weak_king, corner))
+        .min()
+        .unwrap();
+
+    KNOWN_WIN + key.material(strong) + push_close(king, weak_king) + 30 * (7 - corner_distance)
+}
+
+fn kpk(board: &Board, _key: MaterialKey, strong: Color) -> i32 {
+    let pawn = board.pieces(PieceType::Pawn).bit_scan();
+    let (mut king, mut weak_king) = (king(board, strong), king(board, !strong));
+    let mut side_to_move = board.side_to_move();
+
+    // the bitbase is from white's point of view
+    let pawn = match strong {
+        Color::White => pawn,
+        Color::Black => {
+            king = king.mirror_vertically();
+            weak_king = weak_king.mirror_vertically();
+            side_to_move = !side_to_move;
+            pawn.mirror_vertically()
+        }
+    };
+
+    if kpk::probe(side_to_move, king, weak_king, pawn) {
+        KNOWN_WIN + raw_piece_value(PieceType::Pawn) as i32 + pawn.rank() as i32
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::evaluation::endgame::{
+        evaluate, scale_factor, MaterialKey, DRAW_SCALE, KNOWN_WIN, MINOR_UP_SCALE, NORMAL_SCALE,
+        OPPOSITE_BISHOPS_SCALE, PURE_OPPOSITE_BISHOPS_SCALE, ROOK_VS_MINOR_SCALE,
+    };
+    use crate::evaluation::score::Score;
+    use crate::types::color::Color;
+
+    fn evaluate_fen(fen: &str) -> Option<i32> {
+        let board = Board::from_str(fen).unwrap();
+        evaluate(&board, MaterialKey::new(&board))
+    }
+
+    fn scale_fen(fen: &str) -> i32 {
+        let board = Board::from_str(fen).unwrap();
+        scale_factor(&board, MaterialKey::new(&board), Score::new(100, 100))
+    }
+
+    #[test]
+    fn test_material_key() {
+        let board = Board::from_str("8/8/3k4/8/8/2NBK3/8/8 w - - 0 1").unwrap();
+        assert_eq!(
+            MaterialKey::new(&board),
+            MaterialKey::from_code("KBNK", Color::White)
+        );
+        assert_ne!(
+            MaterialKey::new(&board),
+            MaterialKey::from_code("KBNK", Color::Black)
+        );
+
+        let board = Board::from_str("8/8/3k4/3p4/8/4K3/8/8 w - - 0 1").unwrap();
+        assert_eq!(
+            MaterialKey::new(&board),
+            MaterialKey::from_code("KPK", Color::Black)
+        );
+    }
+
+    #[test]
+    fn test_kpk() {
+        assert!(evaluate_fen("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1").unwrap() > KNOWN_WIN);
+        assert_eq!(evaluate_fen("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"), Some(0));
+        assert!(evaluate_fen("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1").unwrap() > KNOWN_WIN);
+
+        // the same positions with colors reversed
+        assert_eq!(evaluate_fen("8/8/8/4p3/4k3/8/4K3/8 b - - 0 1"), Some(0));
+        assert!(evaluate_fen("8/8/8/4p3/4k3/8/4K3/8 w - - 0 1").unwrap() < -KNOWN_WIN);
+    }
+
+    #[test]
+    fn test_mop_up() {
+        // the weak king is pushed to the edge
+        let center = evaluate_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+        let edge = evaluate_fen("3k4/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(center > KNOWN_WIN && edge > center);
+
+        // only the corners of the bishop's color allow a mate
+        let right_corner = evaluate_fen("7k/8/5K2/8/8/8/8/3NB3 w - - 0 1").unwrap();
+        let wrong_corner = evaluate_fen("k7/8/2K5/8/8/8/8/3NB3 w - - 0 1").unwrap();
+        assert!(right_corner > wrong_corner);
+
+        assert_eq!(evaluate_fen("8/8/3k4/8/8/2NNK3/8/8 w - - 0 1"), Some(0));
+        assert_eq!(evaluate_fen("8/8/3k4/8/8/2PNK3/8/8 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn test_scale_factor() {
+        assert_eq!(scale_fen("8/3k4/8/3p4/8/3P4/4K3/8 w - - 0 1"), NORMAL_SCALE);
+
+        // rook pawn with a bishop that doesn't control the promotion square
+        assert_eq!(scale_fen("7k/8/8/7P/8/8/4B3/4K3 w - - 0 1"), DRAW_SCALE);
+        assert_eq!(scale_fen("7k/8/8/7P/8/8/3B4/4K3 w - - 0 1"), NORMAL_SCALE);
+
+        // opposite colored bishops
+        assert_eq!(
+            scale_fen("8/3kb3/8/3p4/2PP4/3B4/4K3/8 w - - 0 1"),
+            PURE_OPPOSITE_BISHOPS_SCALE
+        );
+        assert_eq!(
+            scale_fen("8/3k4/8/3p4/2PP4/3B4/4K1b1/8 w - - 0 1"),
+            NORMAL_SCALE
+        );
+        assert_eq!(
+            scale_fen("8/r2kb3/8/3p4/2PP4/3B4/4K3/R7 w - - 0 1"),
+            OPPOSITE_BISHOPS_SCALE
+        );
+        // the scale doesn't depend on which side has the extra pawn
+        assert_eq!(
+            scale_fen("8/r2kb3/2pp4/8/3P4/3B4/4K3/R7 b - - 0 1"),
+            OPPOSITE_BISHOPS_SCALE
+        );
+
+        // pawnless
+        assert_eq!(
+            scale_fen("8/3kn3/8/8/8/8/4K3/R7 w - - 0 1"),
+            ROOK_VS_MINOR_SCALE
+        );
+        assert_eq!(
+            scale_fen("8/3kr3/8/8/8/8/4KB2/R7 w - - 0 1"),
+            MINOR_UP_SCALE
+        );
+        assert_eq!(scale_fen("8/3kn3/8/8/8/8/4KB2/8 w - - 0 1"), DRAW_SCALE);
+        assert_eq!(scale_fen("8/3kr3/8/8/8/8/4K3/Q7 w - - 0 1"), NORMAL_SCALE);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::types::color::Color;
+use crate::types::square::{File, Square};
+
+/// One bit per position with white king and pawn against the black king that is won for white,
+/// generated by `tablegen`
+static KPK_BITBASE: [u64; 3072] =
+    unsafe { std::mem::transmute(*include_bytes!(concat!(env!("OUT_DIR"), "/kpk_bitbase"))) };
+
+/// Index of a position in the bitbase, the same as `tablegen::kpk::kpk_index`
+fn index(side_to_move: Color, white_king: Square, black_king: Square, pawn: Square) -> usize {
+    let pawn = (pawn.rank() as usize - 1) * 4 + pawn.file() as usize;
+
+    ((pawn * 2 + side_to_move as usize) * 64 + white_king as usize) * 64 + black_king as usize
+}
+
+/// Whether white wins with king and pawn against the black king
+pub fn probe(side_to_move: Color, white_king: Square, black_king: Square, pawn: Square) -> bool {
+    // the bitbase only contains pawns on files a-d
+    let (white_king, black_king, pawn) = if pawn.file() as u8 > File::D as u8 {
+        (
+            mirror_horizontally(white_king),
+            mirror_horizontally(black_king),
+            mirror_horizontally(pawn),
+        )
+    } else {
+        (white_king, black_king, pawn)
+    };
+
+    let index = index(side_to_move, white_king, black_king, pawn);
+    KPK_BITBASE[index / 64] & (1 << (index % 64)) != 0
+}
+
+fn mirror_horizontally(square: Square) -> Square {
+    Square::from_index(square as u8 ^ 7)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evaluation::endgame::kpk::probe;
+    use crate::types::color::Color;
+    use crate::types::square::Square;
+
+    #[test]
+    fn test_probe() {
+        assert!(probe(Color::White, Square::E6, Square::E8, Square::E5));
+        assert!(!probe(Color::White, Square::E5, Square::E7, Square::E4));
+        assert!(probe(Color::Black, Square::E5, Square::E7, Square::E4));
+        assert!(!probe(Color::White, Square::G6, Square::H8, Square::H5));
+    }
+}
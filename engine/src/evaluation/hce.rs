@@ -1,8 +1,15 @@
 use crate::board::Board;
 use crate::types::color::Color;
-use crate::types::piece::{PieceType, ALL_PIECES};
+use crate::types::piece::PieceType;
 use crate::types::square::Square;
 
+use super::endgame::{self, MaterialKey, NORMAL_SCALE};
+use super::king_safety;
+use super::mobility::{self, KingAttacks};
+use super::params::{EvalParams, DEFAULT_PARAMS, PARAMS};
+use super::pawns::{self, PawnTable};
+use super::score::Score;
+use super::trace::{NoTrace, Trace, Tracer};
 use super::Evaluation;
 
 pub const fn raw_piece_value(piece: PieceType) -> i16 {
@@ -20,108 +27,226 @@ pub const fn raw_piece_value(piece: PieceType) -> i16 {
     }
 }
 
-fn piece_value(piece: PieceType, square: Square, piece_color: Color) -> i16 {
-    let sign = match piece_color {
-        Color::White => 1,
-        Color::Black => -1,
-    };
+/// Game phase with all minor and major pieces on the board
+pub const MAX_PHASE: i32 = 24;
 
-    let piece_value = raw_piece_value(piece);
+/// Contribution of a piece to the game phase
+pub const fn phase_weight(piece: PieceType) -> u8 {
+    match piece {
+        PieceType::Pawn | PieceType::King => 0,
+        PieceType::Knight | PieceType::Bishop => 1,
+        PieceType::Rook => 2,
+        PieceType::Queen => 4,
+    }
+}
 
-    let bonus = piece_square_table(piece, square, piece_color);
+/// Material and piece square value of a piece from white's point of view. The board keeps the
+/// sum over all pieces up to date.
+pub fn psq(color: Color, piece: PieceType, square: Square) -> Score {
+    PSQ[color as usize][piece as usize][square as usize]
+}
 
-    sign * (piece_value + bonus)
+/// Evaluation from white's point of view, interpolated between middlegame and endgame by the
+/// remaining material
+pub fn board_value(board: &Board, pawn_table: &mut PawnTable) -> Evaluation {
+    evaluate(board, pawn_table, &mut NoTrace)
 }
 
-pub fn board_value(board: &Board) -> Evaluation {
-    let mut result: i16 = 0;
-    for piece in ALL_PIECES {
-        for square in board.pieces(piece).iter() {
-            result += piece_value(piece, square, board.piece_at(square).unwrap().color());
-        }
+/// Evaluates the position and records the contribution of every term
+pub fn trace(board: &Board) -> Trace {
+    let mut trace = Trace::default();
+    let key = MaterialKey::new(board);
+    let score = evaluate_terms(board, &mut PawnTable::new(), &mut trace);
+
+    trace.phase = game_phase(board);
+    trace.scale = endgame::scale_factor(board, key, score);
+    trace.endgame = endgame::evaluate(board, key);
+    trace
+}
+
+/// Evaluation from white's point of view that reports every applied weight to `tracer`. Known
+/// endgames are evaluated by their specialised evaluation instead.
+pub fn evaluate<T: Tracer>(
+    board: &Board,
+    pawn_table: &mut PawnTable,
+    tracer: &mut T,
+) -> Evaluation {
+    let key = MaterialKey::new(board);
+    if let Some(value) = endgame::evaluate(board, key) {
+        return Evaluation(value as i16);
     }
-    Evaluation(result)
+
+    let score = evaluate_terms(board, pawn_table, tracer);
+    let scale = endgame::scale_factor(board, key, score);
+
+    Evaluation(taper(score, game_phase(board), scale) as i16)
 }
 
-// See: https://www.chessprogramming.org/Simplified_Evaluation_Function
+/// Sum of all evaluation terms from white's point of view
+fn evaluate_terms<T: Tracer>(board: &Board, pawn_table: &mut PawnTable, tracer: &mut T) -> Score {
+    if T::ENABLED {
+        trace_psq(board, tracer);
+    }
 
-pub fn piece_square_table(piece: PieceType, square: Square, piece_color: Color) -> i16 {
-    let square_index = square as usize;
+    let mut king_attacks = KingAttacks::default();
+    board.psq()
+        + pawns::evaluate(board, pawn_table, tracer)
+        + mobility::evaluate(board, &mut king_attacks, tracer)
+        + king_safety::evaluate(board, &king_attacks, tracer)
+}
 
-    let rank = square.to_rank() as usize;
-    let file = square.to_file() as usize;
+/// Interpolates between the middlegame and the endgame score by the game phase, the endgame score
+/// is scaled by `scale` out of [`NORMAL_SCALE`] first
+pub fn taper(score: Score, phase: i32, scale: i32) -> i32 {
+    let eg = score.eg() as i32 * scale / NORMAL_SCALE;
+    Score::new(score.mg(), eg as i16).taper(phase, MAX_PHASE)
+}
 
-    let lookup_index = match piece_color {
-        Color::White => (7 - rank) * 8 + file,
-        Color::Black => square_index,
-    };
+/// Game phase of the position between 0 and [`MAX_PHASE`]
+pub fn game_phase(board: &Board) -> i32 {
+    // promotions can raise the phase above the starting material
+    (board.phase() as i32).min(MAX_PHASE)
+}
 
-    (match piece {
-        PieceType::Pawn => PAWNS_TABLE[lookup_index],
-        PieceType::Knight => KNIGHTS_TABLE[lookup_index],
-        PieceType::Bishop => BISHOP_TABLE[lookup_index],
-        PieceType::Rook => ROOK_TABLE[lookup_index],
-        PieceType::Queen => QUEEN_TABLE[lookup_index],
-        PieceType::King => 0,
-    }) as i16
+/// Records the weights that make up the incrementally updated piece square score of the board
+fn trace_psq(board: &Board, tracer: &mut impl Tracer) {
+    for square in board.combined().iter() {
+        let piece = board.piece_at(square).unwrap();
+        let (color, ty) = (piece.color(), piece.ty());
+        let index = match color {
+            Color::White => square.mirror_vertically(),
+            Color::Black => square,
+        };
+
+        tracer.record(&PARAMS.material[ty as usize], color);
+        tracer.record(&PARAMS.psqt[ty as usize][index as usize], color);
+    }
+}
+
+const PSQ: [[[Score; 64]; 6]; 2] = build_psq(&DEFAULT_PARAMS);
+
+/// Combines the material values and the piece square tables into one table per color. The piece
+/// square tables are laid out as seen from white with a8 first, so white looks them up mirrored.
+const fn build_psq(params: &EvalParams) -> [[[Score; 64]; 6]; 2] {
+    let mut table = [[[Score::ZERO; 64]; 6]; 2];
+
+    let mut piece = 0;
+    while piece < 6 {
+        let material = params.material[piece];
+
+        let mut square = 0;
+        while square < 64 {
+            let white = params.psqt[piece][square ^ 56];
+            table[Color::White as usize][piece][square] =
+                Score::new(material.mg() + white.mg(), material.eg() + white.eg());
+
+            let black = params.psqt[piece][square];
+            table[Color::Black as usize][piece][square] =
+                Score::new(-(material.mg() + black.mg()), -(material.eg() + black.eg()));
+
+            square += 1;
+        }
+        piece += 1;
+    }
+
+    table
 }
 
-#[rustfmt::skip]
-const PAWNS_TABLE: [i8; 64] = [
-    0,  0,  0,  0,  0,  0,  0,  0,
-    50, 50, 50, 50, 50, 50, 50, 50,
-    10, 10, 20, 30, 30, 20, 10, 10,
-    5,  5, 10, 25, 25, 10,  5,  5,
-    0,  0,  0, 20, 20,  0,  0,  0,
-    5, -5,-10,  0,  0,-10, -5,  5,
-    5, 10, 10,-20,-20, 10, 10,  5,
-    0,  0,  0,  0,  0,  0,  0,  0
-];
-
-#[rustfmt::skip]
-const KNIGHTS_TABLE: [i8; 64] = [
-    -50,-40,-30,-30,-30,-30,-40,-50,
-    -40,-20,  0,  0,  0,  0,-20,-40,
-    -30,  0, 10, 15, 15, 10,  0,-30,
-    -30,  5, 15, 20, 20, 15,  5,-30,
-    -30,  0, 15, 20, 20, 15,  0,-30,
-    -30,  5, 10, 15, 15, 10,  5,-30,
-    -40,-20,  0,  5,  5,  0,-20,-40,
-    -50,-40,-30,-30,-30,-30,-40,-50,
-];
-
-#[rustfmt::skip]
-const BISHOP_TABLE: [i8; 64] = [
-    -20,-10,-10,-10,-10,-10,-10,-20,
-    -10,  0,  0,  0,  0,  0,  0,-10,
-    -10,  0,  5, 10, 10,  5,  0,-10,
-    -10,  5,  5, 10, 10,  5,  5,-10,
-    -10,  0, 10, 10, 10, 10,  0,-10,
-    -10, 10, 10, 10, 10, 10, 10,-10,
-    -10,  5,  0,  0,  0,  0,  5,-10,
-    -20,-10,-10,-10,-10,-10,-10,-20,
-];
-
-#[rustfmt::skip]
-const ROOK_TABLE: [i8; 64] = [
-    0,  0,  0,  0,  0,  0,  0,  0,
-    5, 10, 10, 10, 10, 10, 10,  5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-    0,  0,  0,  5,  5,  0,  0,  0
-];
-
-#[rustfmt::skip]
-const QUEEN_TABLE: [i8; 64] = [
-    -20,-10,-10, -5, -5,-10,-10,-20,
-    -10,  0,  0,  0,  0,  0,  0,-10,
-    -10,  0,  5,  5,  5,  5,  0,-10,
-    -5,  0,  5,  5,  5,  5,  0, -5,
-    0,  0,  5,  5,  5,  5,  0, -5,
-    -10,  5,  5,  5,  5,  5,  0,-10,
-    -10,  0,  5,  0,  0,  0,  0,-10,
-    -20,-10,-10, -5, -5,-10,-10,-20
-];
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::evaluation::endgame::NORMAL_SCALE;
+    use crate::evaluation::hce::{board_value, trace};
+    use crate::evaluation::pawns::PawnTable;
+    use crate::evaluation::score::Score;
+    use crate::evaluation::trace::Term;
+    use crate::evaluation::Evaluation;
+    use crate::types::color::Color;
+
+    #[test]
+    fn test_symmetric() {
+        let mut pawn_table = PawnTable::new();
+
+        assert_eq!(
+            board_value(&Board::default(), &mut pawn_table),
+            Evaluation::EQUALITY
+        );
+
+        let white =
+            Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let black =
+            Board::from_str("r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1")
+                .unwrap();
+        assert_eq!(
+            board_value(&white, &mut pawn_table),
+            -board_value(&black, &mut pawn_table)
+        );
+    }
+
+    #[test]
+    fn test_king_placement_depends_on_phase() {
+        let mut pawn_table = PawnTable::new();
+
+        // with all pieces on the board the king belongs on its back rank
+        let middlegame = Board::default();
+        let middlegame_centralised =
+            Board::from_str("rnbqkbnr/pppppppp/8/8/4K3/8/PPPPPPPP/RNBQ1BNR w kq - 0 1").unwrap();
+        assert!(
+            board_value(&middlegame, &mut pawn_table)
+                > board_value(&middlegame_centralised, &mut pawn_table)
+        );
+
+        // without pieces it belongs in the center
+        let endgame = Board::from_str("4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        let endgame_centralised = Board::from_str("4k3/8/8/8/4K3/8/5PPP/8 w - - 0 1").unwrap();
+        assert!(
+            board_value(&endgame, &mut pawn_table)
+                < board_value(&endgame_centralised, &mut pawn_table)
+        );
+    }
+
+    #[test]
+    fn test_trace_matches_evaluation() {
+        let fens = [
+            Board::STARTING_POS_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "6k1/5ppp/8/3P4/8/8/5PPP/6K1 b - - 0 1",
+            // scaled for opposite colored bishops
+            "8/3kb3/8/3p4/2PP4/3B4/4K3/8 w - - 0 1",
+            // evaluated as a known endgame
+            "8/8/3k4/8/8/2NBK3/8/8 w - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::from_str(fen).unwrap();
+            let trace = trace(&board);
+
+            let expected = board_value(&board, &mut PawnTable::new());
+            assert_eq!(
+                Evaluation::from_centipawns(trace.evaluation()),
+                expected,
+                "{fen}"
+            );
+        }
+
+        let trace = trace(&Board::default());
+        assert_eq!(trace.phase(), 24);
+        assert_eq!(trace.scale(), NORMAL_SCALE);
+        assert_eq!(trace.endgame(), None);
+        assert_eq!(
+            trace.term(Term::Material, Color::White),
+            Score::new(
+                8 * 82 + 2 * 337 + 2 * 365 + 2 * 477 + 1025,
+                8 * 94 + 2 * 281 + 2 * 297 + 2 * 512 + 936
+            )
+        );
+        assert_eq!(
+            trace.term(Term::Material, Color::White),
+            trace.term(Term::Material, Color::Black)
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use crate::board::Board;
+use crate::types::bitboard::BitBoard;
+use crate::types::color::{Color, ALL_COLORS};
+use crate::types::piece::PieceType;
+use crate::types::square::{File, Square};
+
+use super::mobility::KingAttacks;
+use super::params::PARAMS;
+use super::pawns::ranks_in_front;
+use super::score::Score;
+use super::trace::Tracer;
+
+/// Attacks by a single piece are ignored, it can rarely mate on its own
+const MIN_KING_ATTACKERS: u8 = 2;
+
+/// King safety from white's point of view, using the attacks on the king zones collected by the
+/// mobility evaluation
+pub fn evaluate<T: Tracer>(board: &Board, king_attacks: &KingAttacks, tracer: &mut T) -> Score {
+    let mut score = Score::ZERO;
+
+    for color in ALL_COLORS {
+        let king = (board.pieces(PieceType::King) & board.occupancies(color)).bit_scan();
+
+        let mut safety = shelter(board, color, king, tracer);
+
+        if king_attacks.attackers[color] >= MIN_KING_ATTACKERS {
+            let units = (king_attacks.units[color] as usize).min(PARAMS.king_danger.len() - 1);
+            safety += tracer.apply(&PARAMS.king_danger[units], color);
+        }
+
+        match color {
+            Color::White => score += safety,
+            Color::Black => score -= safety,
+        }
+    }
+
+    score
+}
+
+/// Pawn shield and open files on the king file and the files next to it
+fn shelter<T: Tracer>(board: &Board, color: Color, king: Square, tracer: &mut T) -> Score {
+    let pawns = board.pieces(PieceType::Pawn);
+    let own_pawns = pawns & board.occupancies(color);
+    let in_front = ranks_in_front(color, king);
+
+    let king_file = king.file() as usize;
+    let files = king_file.saturating_sub(1)..=(king_file + 1).min(File::H as usize);
+
+    let mut score = Score::ZERO;
+    for file in files {
+        let file = BitBoard::ALL_FILES[file];
+
+        let distance = (own_pawns & file & in_front)
+            .iter()
+            .map(|square| (square.rank() as u8).abs_diff(king.rank() as u8))
+            .min()
+            .unwrap_or(u8::MAX);
+        let shield = (distance as usize - 1).min(PARAMS.pawn_shield.len() - 1);
+        score += tracer.apply(&PARAMS.pawn_shield[shield], color);
+
+        if (pawns & file).is_empty() {
+            score += tracer.apply(&PARAMS.open_file, color);
+        } else if (own_pawns & file).is_empty() {
+            score += tracer.apply(&PARAMS.semi_open_file, color);
+        }
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::evaluation::king_safety::evaluate;
+    use crate::evaluation::mobility::KingAttacks;
+    use crate::evaluation::params::PARAMS;
+    use crate::evaluation::score::Score;
+    use crate::evaluation::trace::NoTrace;
+    use crate::types::color::Color;
+
+    #[test]
+    fn test_pawn_shield() {
+        let king_attacks = KingAttacks::default();
+
+        let intact = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert_eq!(evaluate(&intact, &king_attacks, &mut NoTrace), Score::ZERO);
+
+        let advanced = Board::from_str("6k1/5ppp/8/8/8/6P1/5P1P/6K1 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate(&advanced, &king_attacks, &mut NoTrace),
+            PARAMS.pawn_shield[1] - PARAMS.pawn_shield[0]
+        );
+
+        let open = Board::from_str("1k6/ppp5/8/8/8/8/5P1P/6K1 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate(&open, &king_attacks, &mut NoTrace),
+            PARAMS.pawn_shield[2] - PARAMS.pawn_shield[0] + PARAMS.open_file
+        );
+
+        let semi_open = Board::from_str("6k1/5ppp/8/8/8/8/5P1P/6K1 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate(&semi_open, &king_attacks, &mut NoTrace),
+            PARAMS.pawn_shield[2] - PARAMS.pawn_shield[0] + PARAMS.semi_open_file
+        );
+    }
+
+    #[test]
+    fn test_attack_units() {
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+
+        let mut king_attacks = KingAttacks::default();
+        king_attacks.units[Color::Black] = 12;
+        king_attacks.attackers[Color::Black] = 1;
+        assert_eq!(evaluate(&board, &king_attacks, &mut NoTrace), Score::ZERO);
+
+        king_attacks.attackers[Color::Black] = 2;
+        assert_eq!(
+            evaluate(&board, &king_attacks, &mut NoTrace),
+            -PARAMS.king_danger[12]
+        );
+    }
+}
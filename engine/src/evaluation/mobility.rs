@@ -0,0 +1,164 @@
+use crate::board::Board;
+use crate::movegen::attacks::{
+    get_bishop_attacks, get_king_attacks, get_knight_attacks, get_rook_attacks,
+};
+use crate::types::bitboard::BitBoard;
+use crate::types::color::{Color, PerColor, ALL_COLORS};
+use crate::types::piece::PieceType;
+use crate::types::square::Square;
+
+use super::params::PARAMS;
+use super::pawns::pawn_attacks;
+use super::score::Score;
+use super::trace::Tracer;
+
+/// Attack units per square of the enemy king zone attacked by a piece, indexed by piece type
+const KING_ATTACK_WEIGHTS: [u16; 6] = [0, 2, 2, 3, 5, 0];
+
+/// Attacks on the king zone of each side, collected while computing the mobility
+#[derive(Debug, Default)]
+pub struct KingAttacks {
+    /// Weighted number of attacked king zone squares
+    pub units: PerColor<u16>,
+    /// Number of pieces attacking the king zone
+    pub attackers: PerColor<u8>,
+}
+
+/// Mobility of the knights, bishops, rooks and queens from white's point of view. Squares
+/// occupied by own pieces or attacked by enemy pawns do not count as reachable.
+pub fn evaluate<T: Tracer>(board: &Board, king_attacks: &mut KingAttacks, tracer: &mut T) -> Score {
+    let mut score = Score::ZERO;
+
+    for color in ALL_COLORS {
+        let own = board.occupancies(color);
+        let enemy_pawns = board.pieces(PieceType::Pawn) & board.occupancies(!color);
+        let area = !(own | pawn_attacks(enemy_pawns, !color));
+
+        let enemy_king = (board.pieces(PieceType::King) & board.occupancies(!color)).bit_scan();
+        let king_zone = king_zone(enemy_king);
+
+        let mut mobility = Score::ZERO;
+        for piece in [
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+        ] {
+            for square in (board.pieces(piece) & own).iter() {
+                let attacks = attacks(piece, square, board.combined());
+                let bonus = mobility_bonus(piece, (attacks & area).count() as usize);
+                mobility += tracer.apply(bonus, color);
+
+                let zone_attacks = (attacks & king_zone).count() as u16;
+                if zone_attacks > 0 {
+                    king_attacks.units[!color] +=
+                        KING_ATTACK_WEIGHTS[piece as usize] * zone_attacks;
+                    king_attacks.attackers[!color] += 1;
+                }
+            }
+        }
+
+        match color {
+            Color::White => score += mobility,
+            Color::Black => score -= mobility,
+        }
+    }
+
+    score
+}
+
+/// The squares around the king including its own square
+pub fn king_zone(king: Square) -> BitBoard {
+    get_king_attacks(king) | BitBoard::from_square(king)
+}
+
+fn attacks(piece: PieceType, square: Square, occupancy: BitBoard) -> BitBoard {
+    match piece {
+        PieceType::Knight => get_knight_attacks(square),
+        PieceType::Bishop => get_bishop_attacks(square, occupancy),
+        PieceType::Rook => get_rook_attacks(square, occupancy),
+        PieceType::Queen => {
+            get_bishop_attacks(square, occupancy) | get_rook_attacks(square, occupancy)
+        }
+        PieceType::Pawn | PieceType::King => unreachable!("mobility of {piece:?}"),
+    }
+}
+
+fn mobility_bonus(piece: PieceType, squares: usize) -> &'static Score {
+    match piece {
+        PieceType::Knight => &PARAMS.knight_mobility[squares],
+        PieceType::Bishop => &PARAMS.bishop_mobility[squares],
+        PieceType::Rook => &PARAMS.rook_mobility[squares],
+        PieceType::Queen => &PARAMS.queen_mobility[squares],
+        PieceType::Pawn | PieceType::King => unreachable!("mobility of {piece:?}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::evaluation::mobility::{evaluate, KingAttacks};
+    use crate::evaluation::params::PARAMS;
+    use crate::evaluation::score::Score;
+    use crate::evaluation::trace::NoTrace;
+    use crate::types::color::Color;
+
+    #[test]
+    fn test_knight_mobility() {
+        let mut king_attacks = KingAttacks::default();
+
+        // the knight on a1 reaches b3 and c2, but b3 is attacked by the pawn on a4
+        let board = Board::from_str("4k3/8/8/8/p7/8/8/N3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate(&board, &mut king_attacks, &mut NoTrace),
+            PARAMS.knight_mobility[1]
+        );
+
+        let board = Board::from_str("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate(&board, &mut king_attacks, &mut NoTrace),
+            PARAMS.knight_mobility[8]
+        );
+    }
+
+    #[test]
+    fn test_symmetric() {
+        let white =
+            Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let black =
+            Board::from_str("r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1")
+                .unwrap();
+
+        let mut white_attacks = KingAttacks::default();
+        let mut black_attacks = KingAttacks::default();
+
+        let score = evaluate(&white, &mut white_attacks, &mut NoTrace);
+        assert_ne!(score, Score::ZERO);
+        assert_eq!(evaluate(&black, &mut black_attacks, &mut NoTrace), -score);
+
+        assert_eq!(
+            white_attacks.units[Color::White],
+            black_attacks.units[Color::Black]
+        );
+        assert_eq!(
+            white_attacks.units[Color::Black],
+            black_attacks.units[Color::White]
+        );
+    }
+
+    #[test]
+    fn test_king_attacks() {
+        let mut king_attacks = KingAttacks::default();
+
+        // the queen attacks h7 and h8 next to the black king, the knight attacks f7 and h7
+        let board = Board::from_str("6k1/8/8/6N1/8/8/8/K6Q w - - 0 1").unwrap();
+        evaluate(&board, &mut king_attacks, &mut NoTrace);
+
+        assert_eq!(king_attacks.attackers[Color::Black], 2);
+        assert_eq!(king_attacks.units[Color::Black], 2 * 5 + 2 * 2);
+        assert_eq!(king_attacks.attackers[Color::White], 0);
+    }
+}
@@ -1,3 +1,9 @@
+pub mod accumulator;
+pub mod simd;
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::path::Path;
+
 use crate::types::color::Color;
 use crate::types::piece::{PieceType, NUM_PIECES};
 use crate::types::square::{Square, NUM_SQUARES};
@@ -6,10 +12,31 @@ pub type Matrix<T, const ROWS: usize, const COLS: usize> = [[T; ROWS]; COLS];
 
 pub type Vector<T, const ROWS: usize> = [T; ROWS];
 
+/// Network embedded into the binary. The build script takes it from the file given by the
+/// `EVALFILE` environment variable and writes an all zero network otherwise.
+static EMBEDDED_NETWORK: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/default.nnue"));
+
+/// Name of the embedded network in the `EvalFile` option
+pub const EMBEDDED_NETWORK_NAME: &str = "<embedded>";
+
+/// Identifies network files
+const NETWORK_MAGIC: [u8; 4] = *b"SNUE";
+/// Has to be incremented whenever the file layout changes
+const NETWORK_VERSION: u32 = 1;
+/// magic, version, number of features, hidden size, QA, QB and SCALE
+const HEADER_SIZE: usize = 4 + 6 * 4;
+
 pub struct FeatureIndex(usize);
 
 impl FeatureIndex {
-    pub fn new(color: Color, piece: PieceType, square: Square) -> Self {
+    /// Index of a piece as seen from `perspective`: the colors are relative to the perspective
+    /// and the board is mirrored for black, so both sides share the same weights
+    pub fn new(perspective: Color, color: Color, piece: PieceType, square: Square) -> Self {
+        let (color, square) = match perspective {
+            Color::White => (color, square),
+            Color::Black => (!color, square.mirror_vertically()),
+        };
+
         Self(
             color as usize * (NUM_PIECES * NUM_SQUARES)
                 + piece as usize * NUM_SQUARES
@@ -18,25 +45,20 @@ impl FeatureIndex {
     }
 }
 
-// TODO: train network and replace with new model
-static MODEL: NNUE = NNUE {
-    input_layer: Layer {
-        weights: [[0; NNUE::HIDDEN]; NNUE::FEATURES],
-        biases: [[0; NNUE::HIDDEN]; 1],
-    },
-    hidden_layer: Layer {
-        weights: [[0; 1]; { NNUE::HIDDEN * 2 }],
-        biases: [[0; 1]; 1],
-    },
-};
-
 /// Sequential Neural network with dense layers
 ///
 /// Architecture is (768 -> 128)x2 -> 1
+///
+/// Networks are stored as
+/// - the magic `SNUE`
+/// - version, number of features, hidden size, `QA`, `QB` and `SCALE` as little endian `u32`
+/// - the parameters as little endian `i16`: input weights grouped by feature, input biases,
+///   output weights of the side to move followed by those of the other side, output bias
 #[repr(C)]
 pub struct NNUE {
     input_layer: Layer<{ NNUE::FEATURES }, { NNUE::HIDDEN }>,
     hidden_layer: Layer<{ NNUE::HIDDEN * 2 }, 1>,
+    quantisation: Quantisation,
 }
 
 pub struct Layer<const INPUTS: usize, const OUTPUTS: usize> {
@@ -44,67 +66,326 @@ pub struct Layer<const INPUTS: usize, const OUTPUTS: usize> {
     biases: Matrix<i16, OUTPUTS, 1>,
 }
 
-#[derive(Debug, Clone)]
+/// Constants the network was quantised with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Quantisation {
+    /// Scale of the input layer and the upper bound of the activation
+    qa: i32,
+    /// Scale of the output layer
+    qb: i32,
+    /// Conversion of the output to centipawns
+    scale: i32,
+}
+
+#[derive(Debug)]
+pub enum LoadNetworkError {
+    Io(std::io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    ArchitectureMismatch { features: u32, hidden: u32 },
+    InvalidQuantisation,
+    SizeMismatch { expected: usize, actual: usize },
+}
+
+impl Display for LoadNetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadNetworkError::Io(err) => write!(f, "{err}"),
+            LoadNetworkError::InvalidMagic => write!(f, "not a network file"),
+            LoadNetworkError::UnsupportedVersion(version) => {
+                write!(f, "unsupported network version {version}")
+            }
+            LoadNetworkError::ArchitectureMismatch { features, hidden } => write!(
+                f,
+                "network has {features} features and {hidden} hidden neurons, expected {} and {}",
+                NNUE::FEATURES,
+                NNUE::HIDDEN
+            ),
+            LoadNetworkError::InvalidQuantisation => {
+                write!(
+                    f,
+                    "quantisation constants have to be positive and QA has to fit into 16 bits"
+                )
+            }
+            LoadNetworkError::SizeMismatch { expected, actual } => {
+                write!(f, "network has {actual} bytes, expected {expected}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NNUEAccumulator {
     values: Vector<i16, { NNUE::HIDDEN }>,
 }
 
-impl Default for NNUEAccumulator {
-    fn default() -> Self {
+impl NNUEAccumulator {
+    pub fn new(network: &NNUE) -> Self {
         Self {
-            values: MODEL.input_layer.biases[0],
+            values: network.input_layer.biases[0],
         }
     }
+
+    pub fn set_feature(&mut self, network: &NNUE, index: FeatureIndex) {
+        simd::add_assign(&mut self.values, &network.input_layer.weights[index.0]);
+    }
+
+    pub fn unset_feature(&mut self, network: &NNUE, index: FeatureIndex) {
+        simd::sub_assign(&mut self.values, &network.input_layer.weights[index.0]);
+    }
 }
 
-impl NNUEAccumulator {
-    pub fn set_feature(&mut self, index: FeatureIndex) {
-        for (elem, weight) in self
-            .values
-            .iter_mut()
-            .zip(&MODEL.input_layer.weights[index.0])
+impl NNUE {
+    const FEATURES: usize = 768;
+    const HIDDEN: usize = 128;
+
+    /// Size of a network file in bytes
+    const FILE_SIZE: usize =
+        HEADER_SIZE + 2 * (Self::FEATURES * Self::HIDDEN + Self::HIDDEN + 2 * Self::HIDDEN + 1);
+
+    /// Returns the network embedded into the binary
+    pub fn embedded() -> Box<Self> {
+        Self::from_bytes(EMBEDDED_NETWORK).expect("the embedded network is valid")
+    }
+
+    /// Whether the embedded network is the all zero placeholder, i.e. no `EVALFILE` was given
+    /// at build time
+    pub fn embedded_is_placeholder() -> bool {
+        EMBEDDED_NETWORK[HEADER_SIZE..]
+            .iter()
+            .all(|&byte| byte == 0)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Box<Self>, LoadNetworkError> {
+        let bytes = std::fs::read(path).map_err(LoadNetworkError::Io)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Parses a network in the format described at [`NNUE`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Box<Self>, LoadNetworkError> {
+        if bytes.len() < HEADER_SIZE || bytes[..4] != NETWORK_MAGIC {
+            return Err(LoadNetworkError::InvalidMagic);
+        }
+
+        let header = |index: usize| {
+            let offset = 4 + 4 * index;
+            u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+        };
+
+        let version = header(0);
+        if version != NETWORK_VERSION {
+            return Err(LoadNetworkError::UnsupportedVersion(version));
+        }
+
+        let (features, hidden) = (header(1), header(2));
+        if features as usize != Self::FEATURES || hidden as usize != Self::HIDDEN {
+            return Err(LoadNetworkError::ArchitectureMismatch { features, hidden });
+        }
+
+        let quantisation = Quantisation {
+            qa: header(3) as i32,
+            qb: header(4) as i32,
+            scale: header(5) as i32,
+        };
+        if quantisation.qa <= 0
+            || quantisation.qa > i16::MAX as i32
+            || quantisation.qb <= 0
+            || quantisation.scale <= 0
         {
-            *elem += *weight;
+            return Err(LoadNetworkError::InvalidQuantisation);
+        }
+
+        if bytes.len() != Self::FILE_SIZE {
+            return Err(LoadNetworkError::SizeMismatch {
+                expected: Self::FILE_SIZE,
+                actual: bytes.len(),
+            });
         }
-    }
 
-    pub fn unset_feature(&mut self, index: FeatureIndex) {
-        for (elem, weight) in self
-            .values
+        let mut network = Self::zeroed();
+        network.quantisation = quantisation;
+
+        let parameters = bytes[HEADER_SIZE..]
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]));
+
+        let input_layer = &mut network.input_layer;
+        let hidden_layer = &mut network.hidden_layer;
+        let destinations = input_layer
+            .weights
             .iter_mut()
-            .zip(&MODEL.input_layer.weights[index.0])
-        {
-            *elem -= *weight;
+            .flatten()
+            .chain(input_layer.biases.iter_mut().flatten())
+            .chain(hidden_layer.weights.iter_mut().flatten())
+            .chain(hidden_layer.biases.iter_mut().flatten());
+
+        for (destination, parameter) in destinations.zip(parameters) {
+            *destination = parameter;
         }
+
+        Ok(network)
+    }
+
+    /// Allocates a network with all parameters zero directly on the heap, as it is too large
+    /// for the stack of some threads
+    fn zeroed() -> Box<Self> {
+        // SAFETY: the network only consists of integers, for which all zero bits are valid
+        unsafe { Box::<Self>::new_zeroed().assume_init() }
+    }
+
+    pub fn evaluate(&self, stm: &NNUEAccumulator, nstm: &NNUEAccumulator) -> i32 {
+        let Quantisation { qa, qb, scale } = self.quantisation;
+
+        let (stm_weights, nstm_weights) = self
+            .hidden_layer
+            .weights
+            .as_flattened()
+            .split_at(Self::HIDDEN);
+
+        // the activation bound fits into i16, which is checked when loading the network
+        let mut output = simd::clipped_relu_dot(&stm.values, stm_weights, qa as i16)
+            + simd::clipped_relu_dot(&nstm.values, nstm_weights, qa as i16);
+
+        output += self.hidden_layer.biases[0][0] as i32;
+
+        // the header only bounds qb and scale by i32, so their products need i64
+        let output = output as i64 * scale as i64 / (qa as i64 * qb as i64);
+
+        output.clamp(i32::MIN as i64, i32::MAX as i64) as i32
     }
 }
 
-impl NNUE {
-    const FEATURES: usize = 768;
-    const HIDDEN: usize = 128;
+impl Debug for NNUE {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NNUE")
+            .field("features", &Self::FEATURES)
+            .field("hidden", &Self::HIDDEN)
+            .field("quantisation", &self.quantisation)
+            .finish_non_exhaustive()
+    }
+}
 
-    const SCALE: i32 = 400;
-    const QA: i32 = 255;
-    const QB: i32 = 255;
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::evaluation::nnue::{
+        FeatureIndex, LoadNetworkError, NNUEAccumulator, HEADER_SIZE, NETWORK_MAGIC,
+        NETWORK_VERSION, NNUE,
+    };
+    use crate::types::color::Color;
+    use crate::types::piece::PieceType;
+    use crate::types::square::Square;
 
-    pub fn evaluate(stm: &NNUEAccumulator, nstm: &NNUEAccumulator) -> i32 {
-        let mut output: i32 = 0;
+    /// Network whose parameters repeat a small pattern, such that neighbouring parameters differ
+    pub(crate) fn counting_network(qa: u32, qb: u32, scale: u32) -> Vec<u8> {
+        let mut bytes = NETWORK_MAGIC.to_vec();
+        for value in [
+            NETWORK_VERSION,
+            NNUE::FEATURES as u32,
+            NNUE::HIDDEN as u32,
+            qa,
+            qb,
+            scale,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
 
-        let (stm_weights, nstm_weights) = MODEL.hidden_layer.weights.split_at(Self::HIDDEN);
+        let num_parameters = (NNUE::FILE_SIZE - HEADER_SIZE) / 2;
+        for i in 0..num_parameters {
+            bytes.extend_from_slice(&((i % 7) as i16 - 3).to_le_bytes());
+        }
 
-        output += stm
-            .values
-            .iter()
-            .zip(stm_weights)
-            .chain(nstm.values.iter().zip(nstm_weights))
-            .map(|(&input, &[weight])| i32::from(input).clamp(0, Self::QA) * i32::from(weight))
-            .sum::<i32>();
+        bytes
+    }
+
+    #[test]
+    fn test_embedded_network_is_valid() {
+        let network = NNUE::embedded();
+        let accumulator = NNUEAccumulator::new(&network);
+        let evaluation = network.evaluate(&accumulator, &accumulator);
+
+        if NNUE::embedded_is_placeholder() {
+            assert_eq!(evaluation, 0);
+        }
+    }
+
+    #[test]
+    fn test_parameter_order() {
+        let network = NNUE::from_bytes(&counting_network(255, 64, 400)).unwrap();
+
+        let parameter = |index: usize| (index % 7) as i16 - 3;
+        let num_input_weights = NNUE::FEATURES * NNUE::HIDDEN;
+
+        assert_eq!(network.input_layer.weights[0][1], parameter(1));
+        assert_eq!(network.input_layer.weights[1][0], parameter(NNUE::HIDDEN));
+        assert_eq!(
+            network.input_layer.biases[0][5],
+            parameter(num_input_weights + 5)
+        );
+        assert_eq!(
+            network.hidden_layer.weights[NNUE::HIDDEN][0],
+            parameter(num_input_weights + 2 * NNUE::HIDDEN)
+        );
+        assert_eq!(
+            network.hidden_layer.biases[0][0],
+            parameter(num_input_weights + 3 * NNUE::HIDDEN)
+        );
+
+        let mut accumulator = NNUEAccumulator::new(&network);
+        let index = || FeatureIndex::new(Color::Black, Color::White, PieceType::Knight, Square::G1);
+        let before = accumulator.values;
+        accumulator.set_feature(&network, index());
+        accumulator.unset_feature(&network, index());
+        assert_eq!(accumulator.values, before);
+    }
+
+    #[test]
+    fn test_large_quantisation() {
+        let max = i32::MAX as u32;
+        let network = NNUE::from_bytes(&counting_network(255, max, max)).unwrap();
+        let reference = NNUE::from_bytes(&counting_network(255, 1, 1)).unwrap();
+
+        // the scale cancels out against qb
+        let accumulator = NNUEAccumulator::new(&network);
+        assert_eq!(
+            network.evaluate(&accumulator, &accumulator),
+            reference.evaluate(&accumulator, &accumulator)
+        );
+    }
+
+    #[test]
+    fn test_invalid_networks_are_rejected() {
+        let valid = counting_network(255, 64, 400);
+
+        let mut bytes = valid.clone();
+        bytes[0] = b'X';
+        assert!(matches!(
+            NNUE::from_bytes(&bytes),
+            Err(LoadNetworkError::InvalidMagic)
+        ));
+
+        let mut bytes = valid.clone();
+        bytes[4] = 2;
+        assert!(matches!(
+            NNUE::from_bytes(&bytes),
+            Err(LoadNetworkError::UnsupportedVersion(2))
+        ));
 
-        output += MODEL.hidden_layer.biases[0][0] as i32;
+        let mut bytes = valid.clone();
+        bytes[12] = 0;
+        assert!(matches!(
+            NNUE::from_bytes(&bytes),
+            Err(LoadNetworkError::ArchitectureMismatch { hidden: 0, .. })
+        ));
 
-        output *= Self::SCALE;
-        output /= Self::QA * Self::QB;
+        assert!(matches!(
+            NNUE::from_bytes(&counting_network(0, 64, 400)),
+            Err(LoadNetworkError::InvalidQuantisation)
+        ));
 
-        output
+        let bytes = &valid[..valid.len() - 2];
+        assert!(matches!(
+            NNUE::from_bytes(bytes),
+            Err(LoadNetworkError::SizeMismatch { .. })
+        ));
     }
 }
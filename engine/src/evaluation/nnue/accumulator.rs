@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use crate::board::Board;
+use crate::evaluation::nnue::{FeatureIndex, NNUEAccumulator, NNUE};
+use crate::types::chess_move::{Move, MoveFlag};
+use crate::types::color::{Color, PerColor, ALL_COLORS};
+use crate::types::piece::{PieceType, ALL_PIECES};
+use crate::types::square::{File, Square};
+
+/// Accumulators of the positions along the current search path, one per perspective and ply.
+///
+/// Instead of summing the weights of all pieces for every evaluation, each move only adds and
+/// removes the features of the pieces it moves.
+pub struct AccumulatorStack {
+    network: Arc<NNUE>,
+    stack: Vec<PerColor<NNUEAccumulator>>,
+}
+
+impl AccumulatorStack {
+    pub fn new(network: Arc<NNUE>, board: &Board) -> Self {
+        let accumulators = PerColor::new(ALL_COLORS.map(|perspective| {
+            let mut accumulator = NNUEAccumulator::new(&network);
+
+            for piece in ALL_PIECES {
+                for square in board.pieces(piece).iter() {
+                    let color = board.piece_at(square).unwrap().color();
+                    accumulator.set_feature(
+                        &network,
+                        FeatureIndex::new(perspective, color, piece, square),
+                    );
+                }
+            }
+
+            accumulator
+        }));
+
+        Self {
+            network,
+            stack: vec![accumulators],
+        }
+    }
+
+    /// Pushes the accumulators of the position after `mov`. Has to be called before the move is
+    /// applied to `board`.
+    pub fn push(&mut self, board: &Board, mov: Move) {
+        let mut accumulators = self.current().clone();
+        let network = &*self.network;
+
+        let mut update = |add: bool, color: Color, piece: PieceType, square: Square| {
+            for perspective in ALL_COLORS {
+                let index = FeatureIndex::new(perspective, color, piece, square);
+                if add {
+                    accumulators[perspective].set_feature(network, index);
+                } else {
+                    accumulators[perspective].unset_feature(network, index);
+                }
+            }
+        };
+
+        let us = board.side_to_move();
+        let piece = board.piece_at(mov.from()).unwrap().ty();
+        let placed_piece = mov.promotion().map_or(piece, |p| p.as_piece_type());
+
+        if mov.flag() == MoveFlag::EnPassant {
+            let capture_square = mov.to().forward(!us).unwrap();
+            update(false, !us, PieceType::Pawn, capture_square);
+        } else if let Some(captured) = board.piece_at(mov.to()) {
+            update(false, !us, captured.ty(), mov.to());
+        }
+
+        update(false, us, piece, mov.from());
+        update(true, us, placed_piece, mov.to());
+
+        if mov.flag() == MoveFlag::Castling {
+            const CASTLE_CONFIG: [(File, File); 2] = [(File::A, File::D), (File::H, File::F)];
+
+            let backrank = us.backrank();
+            let (rook_start_file, rook_end_file) = CASTLE_CONFIG[mov.to().to_file() as usize / 4];
+
+            update(
+                false,
+                us,
+                PieceType::Rook,
+                Square::from(backrank, rook_start_file),
+            );
+            update(
+                true,
+                us,
+                PieceType::Rook,
+                Square::from(backrank, rook_end_file),
+            );
+        }
+
+        self.stack.push(accumulators);
+    }
+
+    /// Returns to the accumulators before the last pushed move
+    pub fn pop(&mut self) {
+        self.stack.pop();
+        debug_assert!(!self.stack.is_empty());
+    }
+
+    /// Evaluates the current position in centipawns from the perspective of `side_to_move`
+    pub fn evaluate(&self, side_to_move: Color) -> i32 {
+        let accumulators = self.current();
+        self.network
+            .evaluate(&accumulators[side_to_move], &accumulators[!side_to_move])
+    }
+
+    fn current(&self) -> &PerColor<NNUEAccumulator> {
+        self.stack.last().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use crate::board::Board;
+    use crate::evaluation::nnue::accumulator::AccumulatorStack;
+    use crate::evaluation::nnue::test::counting_network;
+    use crate::evaluation::nnue::NNUE;
+
+    #[test]
+    fn test_incremental_updates_match_refresh() {
+        let network: Arc<NNUE> =
+            Arc::from(NNUE::from_bytes(&counting_network(255, 64, 400)).unwrap());
+
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+
+        for fen in fens {
+            let mut board = Board::from_str(fen).unwrap();
+            let mut stack = AccumulatorStack::new(network.clone(), &board);
+            let root = stack.current().clone();
+
+            for mov in board.generate_moves() {
+                stack.push(&board, mov);
+                board.apply_move(mov);
+
+                let refreshed = AccumulatorStack::new(network.clone(), &board);
+                assert_eq!(stack.current(), refreshed.current(), "{fen} {mov}");
+                assert_eq!(
+                    stack.evaluate(board.side_to_move()),
+                    refreshed.evaluate(board.side_to_move())
+                );
+
+                board.undo_move();
+                stack.pop();
+            }
+
+            assert_eq!(stack.current(), &root);
+        }
+    }
+}
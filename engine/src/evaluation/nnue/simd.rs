@@ -0,0 +1,416 @@
+//! Kernels of the network inference. The scalar implementation is the reference, the vectorised
+//! ones are selected at runtime on x86_64 and at compile time on wasm and produce bit-identical
+//! results: accumulator updates wrap around like the vector instructions do.
+
+#[cfg(target_arch = "x86_64")]
+use std::sync::OnceLock;
+
+/// Kernels of one implementation. The vectorised kernels require CPU features, so calling them
+/// is only safe after checking those.
+#[cfg(target_arch = "x86_64")]
+struct Kernels {
+    add_assign: unsafe fn(&mut [i16], &[i16]),
+    sub_assign: unsafe fn(&mut [i16], &[i16]),
+    clipped_relu_dot: unsafe fn(&[i16], &[i16], i16) -> i32,
+}
+
+#[cfg(target_arch = "x86_64")]
+macro_rules! kernels {
+    ($module:ident) => {
+        Kernels {
+            add_assign: $module::add_assign,
+            sub_assign: $module::sub_assign,
+            clipped_relu_dot: $module::clipped_relu_dot,
+        }
+    };
+}
+
+/// The fastest kernels supported by the CPU, the features are detected on first use
+#[cfg(target_arch = "x86_64")]
+fn kernels() -> &'static Kernels {
+    static KERNELS: OnceLock<Kernels> = OnceLock::new();
+
+    KERNELS.get_or_init(|| {
+        if is_x86_feature_detected!("avx2") {
+            kernels!(avx2)
+        } else if is_x86_feature_detected!("sse4.1") {
+            kernels!(sse41)
+        } else {
+            kernels!(scalar)
+        }
+    })
+}
+
+/// Calls the fastest implementation of a kernel supported by the CPU
+macro_rules! dispatch {
+    ($kernel:ident($($arg:expr),*)) => {{
+        #[cfg(target_arch = "x86_64")]
+        {
+            // SAFETY: the kernels were chosen for the features of the CPU
+            unsafe { (kernels().$kernel)($($arg),*) }
+        }
+
+        #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+        {
+            simd128::$kernel($($arg),*)
+        }
+
+        #[cfg(not(any(
+            target_arch = "x86_64",
+            all(target_arch = "wasm32", target_feature = "simd128")
+        )))]
+        {
+            scalar::$kernel($($arg),*)
+        }
+    }};
+}
+
+/// `values += weights` element wise
+pub fn add_assign(values: &mut [i16], weights: &[i16]) {
+    assert_eq!(values.len(), weights.len());
+    dispatch!(add_assign(values, weights))
+}
+
+/// `values -= weights` element wise
+pub fn sub_assign(values: &mut [i16], weights: &[i16]) {
+    assert_eq!(values.len(), weights.len());
+    dispatch!(sub_assign(values, weights))
+}
+
+/// Dot product of the inputs clamped to `[0, max]` with the weights
+pub fn clipped_relu_dot(inputs: &[i16], weights: &[i16], max: i16) -> i32 {
+    assert_eq!(inputs.len(), weights.len());
+    dispatch!(clipped_relu_dot(inputs, weights, max))
+}
+
+mod scalar {
+    pub fn add_assign(values: &mut [i16], weights: &[i16]) {
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_add(*weight);
+        }
+    }
+
+    pub fn sub_assign(values: &mut [i16], weights: &[i16]) {
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_sub(*weight);
+        }
+    }
+
+    pub fn clipped_relu_dot(inputs: &[i16], weights: &[i16], max: i16) -> i32 {
+        inputs
+            .iter()
+            .zip(weights)
+            .map(|(&input, &weight)| i32::from(input.clamp(0, max)) * i32::from(weight))
+            .fold(0, i32::wrapping_add)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    use super::scalar;
+
+    const LANES: usize = 16;
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn add_assign(values: &mut [i16], weights: &[i16]) {
+        let mut values = values.chunks_exact_mut(LANES);
+        let mut weights = weights.chunks_exact(LANES);
+
+        for (value, weight) in (&mut values).zip(&mut weights) {
+            let sum = _mm256_add_epi16(
+                _mm256_loadu_si256(value.as_ptr() as *const __m256i),
+                _mm256_loadu_si256(weight.as_ptr() as *const __m256i),
+            );
+            _mm256_storeu_si256(value.as_mut_ptr() as *mut __m256i, sum);
+        }
+
+        scalar::add_assign(values.into_remainder(), weights.remainder());
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn sub_assign(values: &mut [i16], weights: &[i16]) {
+        let mut values = values.chunks_exact_mut(LANES);
+        let mut weights = weights.chunks_exact(LANES);
+
+        for (value, weight) in (&mut values).zip(&mut weights) {
+            let difference = _mm256_sub_epi16(
+                _mm256_loadu_si256(value.as_ptr() as *const __m256i),
+                _mm256_loadu_si256(weight.as_ptr() as *const __m256i),
+            );
+            _mm256_storeu_si256(value.as_mut_ptr() as *mut __m256i, difference);
+        }
+
+        scalar::sub_assign(values.into_remainder(), weights.remainder());
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn clipped_relu_dot(inputs: &[i16], weights: &[i16], max: i16) -> i32 {
+        let zero = _mm256_setzero_si256();
+        let max_vector = _mm256_set1_epi16(max);
+        let mut sum = _mm256_setzero_si256();
+
+        let inputs = inputs.chunks_exact(LANES);
+        let mut weights = weights.chunks_exact(LANES);
+        let remainder = inputs.remainder();
+
+        for (input, weight) in inputs.zip(&mut weights) {
+            let input = _mm256_loadu_si256(input.as_ptr() as *const __m256i);
+            let clipped = _mm256_min_epi16(_mm256_max_epi16(input, zero), max_vector);
+            let weight = _mm256_loadu_si256(weight.as_ptr() as *const __m256i);
+            // the products of neighbouring lanes are added up as 32 bit integers
+            sum = _mm256_add_epi32(sum, _mm256_madd_epi16(clipped, weight));
+        }
+
+        let mut lanes = [0i32; 8];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+
+        lanes
+            .into_iter()
+            .fold(0, i32::wrapping_add)
+            .wrapping_add(scalar::clipped_relu_dot(
+                remainder,
+                weights.remainder(),
+                max,
+            ))
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse41 {
+    use std::arch::x86_64::*;
+
+    use super::scalar;
+
+    const LANES: usize = 8;
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn add_assign(values: &mut [i16], weights: &[i16]) {
+        let mut values = values.chunks_exact_mut(LANES);
+        let mut weights = weights.chunks_exact(LANES);
+
+        for (value, weight) in (&mut values).zip(&mut weights) {
+            let sum = _mm_add_epi16(
+                _mm_loadu_si128(value.as_ptr() as *const __m128i),
+                _mm_loadu_si128(weight.as_ptr() as *const __m128i),
+            );
+            _mm_storeu_si128(value.as_mut_ptr() as *mut __m128i, sum);
+        }
+
+        scalar::add_assign(values.into_remainder(), weights.remainder());
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn sub_assign(values: &mut [i16], weights: &[i16]) {
+        let mut values = values.chunks_exact_mut(LANES);
+        let mut weights = weights.chunks_exact(LANES);
+
+        for (value, weight) in (&mut values).zip(&mut weights) {
+            let difference = _mm_sub_epi16(
+                _mm_loadu_si128(value.as_ptr() as *const __m128i),
+                _mm_loadu_si128(weight.as_ptr() as *const __m128i),
+            );
+            _mm_storeu_si128(value.as_mut_ptr() as *mut __m128i, difference);
+        }
+
+        scalar::sub_assign(values.into_remainder(), weights.remainder());
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn clipped_relu_dot(inputs: &[i16], weights: &[i16], max: i16) -> i32 {
+        let zero = _mm_setzero_si128();
+        let max_vector = _mm_set1_epi16(max);
+        let mut sum = _mm_setzero_si128();
+
+        let inputs = inputs.chunks_exact(LANES);
+        let mut weights = weights.chunks_exact(LANES);
+        let remainder = inputs.remainder();
+
+        for (input, weight) in inputs.zip(&mut weights) {
+            let input = _mm_loadu_si128(input.as_ptr() as *const __m128i);
+            let clipped = _mm_min_epi16(_mm_max_epi16(input, zero), max_vector);
+            let weight = _mm_loadu_si128(weight.as_ptr() as *const __m128i);
+            sum = _mm_add_epi32(sum, _mm_madd_epi16(clipped, weight));
+        }
+
+        let mut lanes = [0i32; 4];
+        _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, sum);
+
+        lanes
+            .into_iter()
+            .fold(0, i32::wrapping_add)
+            .wrapping_add(scalar::clipped_relu_dot(
+                remainder,
+                weights.remainder(),
+                max,
+            ))
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod simd128 {
+    use std::arch::wasm32::*;
+
+    use super::scalar;
+
+    const LANES: usize = 8;
+
+    pub fn add_assign(values: &mut [i16], weights: &[i16]) {
+        let mut values = values.chunks_exact_mut(LANES);
+        let mut weights = weights.chunks_exact(LANES);
+
+        for (value, weight) in (&mut values).zip(&mut weights) {
+            // SAFETY: both chunks have the size of a vector
+            unsafe {
+                let sum = i16x8_add(
+                    v128_load(value.as_ptr() as *const v128),
+                    v128_load(weight.as_ptr() as *const v128),
+                );
+                v128_store(value.as_mut_ptr() as *mut v128, sum);
+            }
+        }
+
+        scalar::add_assign(values.into_remainder(), weights.remainder());
+    }
+
+    pub fn sub_assign(values: &mut [i16], weights: &[i16]) {
+        let mut values = values.chunks_exact_mut(LANES);
+        let mut weights = weights.chunks_exact(LANES);
+
+        for (value, weight) in (&mut values).zip(&mut weights) {
+            // SAFETY: both chunks have the size of a vector
+            unsafe {
+                let difference = i16x8_sub(
+                    v128_load(value.as_ptr() as *const v128),
+                    v128_load(weight.as_ptr() as *const v128),
+                );
+                v128_store(value.as_mut_ptr() as *mut v128, difference);
+            }
+        }
+
+        scalar::sub_assign(values.into_remainder(), weights.remainder());
+    }
+
+    pub fn clipped_relu_dot(inputs: &[i16], weights: &[i16], max: i16) -> i32 {
+        let zero = i16x8_splat(0);
+        let max_vector = i16x8_splat(max);
+        let mut sum = i32x4_splat(0);
+
+        let inputs = inputs.chunks_exact(LANES);
+        let mut weights = weights.chunks_exact(LANES);
+        let remainder = inputs.remainder();
+
+        for (input, weight) in inputs.zip(&mut weights) {
+            // SAFETY: both chunks have the size of a vector
+            let (input, weight) = unsafe {
+                (
+                    v128_load(input.as_ptr() as *const v128),
+                    v128_load(weight.as_ptr() as *const v128),
+                )
+            };
+            let clipped = i16x8_min(i16x8_max(input, zero), max_vector);
+            sum = i32x4_add(sum, i32x4_dot_i16x8(clipped, weight));
+        }
+
+        i32x4_extract_lane::<0>(sum)
+            .wrapping_add(i32x4_extract_lane::<1>(sum))
+            .wrapping_add(i32x4_extract_lane::<2>(sum))
+            .wrapping_add(i32x4_extract_lane::<3>(sum))
+            .wrapping_add(scalar::clipped_relu_dot(
+                remainder,
+                weights.remainder(),
+                max,
+            ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evaluation::nnue::simd::{self, scalar};
+
+    /// Deterministic pseudo random values covering the whole range of `i16`
+    fn values(len: usize, seed: u64) -> Vec<i16> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as i16
+            })
+            .collect()
+    }
+
+    /// Runs every kernel implementation available on this machine
+    fn check_kernels(
+        add_assign: fn(&mut [i16], &[i16]),
+        sub_assign: fn(&mut [i16], &[i16]),
+        clipped_relu_dot: fn(&[i16], &[i16], i16) -> i32,
+    ) {
+        // lengths that are not multiples of the vector size cover the scalar remainder
+        for (len, seed) in [(128, 1), (256, 2), (37, 3), (7, 4)] {
+            let weights = values(len, seed);
+            let initial = values(len, seed + 100);
+
+            let mut expected = initial.clone();
+            let mut actual = initial.clone();
+            scalar::add_assign(&mut expected, &weights);
+            add_assign(&mut actual, &weights);
+            assert_eq!(actual, expected);
+
+            scalar::sub_assign(&mut expected, &weights);
+            sub_assign(&mut actual, &weights);
+            assert_eq!(actual, expected);
+            assert_eq!(actual, initial);
+
+            for max in [255, 181, i16::MAX] {
+                assert_eq!(
+                    clipped_relu_dot(&initial, &weights, max),
+                    scalar::clipped_relu_dot(&initial, &weights, max)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_dispatched_kernels_match_scalar() {
+        check_kernels(simd::add_assign, simd::sub_assign, simd::clipped_relu_dot);
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_x86_kernels_match_scalar() {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2
+            check_kernels(
+                |values, weights| unsafe { simd::avx2::add_assign(values, weights) },
+                |values, weights| unsafe { simd::avx2::sub_assign(values, weights) },
+                |inputs, weights, max| unsafe {
+                    simd::avx2::clipped_relu_dot(inputs, weights, max)
+                },
+            );
+        }
+
+        if is_x86_feature_detected!("sse4.1") {
+            // SAFETY: the CPU supports SSE4.1
+            check_kernels(
+                |values, weights| unsafe { simd::sse41::add_assign(values, weights) },
+                |values, weights| unsafe { simd::sse41::sub_assign(values, weights) },
+                |inputs, weights, max| unsafe {
+                    simd::sse41::clipped_relu_dot(inputs, weights, max)
+                },
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    fn test_simd128_kernels_match_scalar() {
+        check_kernels(
+            simd::simd128::add_assign,
+            simd::simd128::sub_assign,
+            simd::simd128::clipped_relu_dot,
+        );
+    }
+}
@@ -0,0 +1,202 @@
+mod default;
+
+pub use default::DEFAULT_PARAMS;
+
+use std::fmt::Write;
+use std::mem::{offset_of, size_of};
+
+use super::score::Score;
+use super::trace::Term;
+
+/// The weights used by the evaluation. Every tracked weight is referenced through this static,
+/// so that its address identifies the weight when tracing the evaluation.
+pub static PARAMS: EvalParams = DEFAULT_PARAMS;
+
+/// All tunable weights of the hand-crafted evaluation.
+///
+/// The struct only consists of scores, so it can be viewed as a flat slice of weights, which is
+/// what the tuner optimises.
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    /// Indexed by piece type
+    pub material: [Score; 6],
+    /// Indexed by piece type and square, laid out as seen from white with a8 first
+    pub psqt: [[Score; 64]; 6],
+
+    /// Pawn with an own pawn in front of it
+    pub doubled: Score,
+    /// Pawn without own pawns on the adjacent files
+    pub isolated: Score,
+    /// Pawn that can't be defended by own pawns and whose stop square is attacked by an enemy pawn
+    pub backward: Score,
+    /// Passed pawn indexed by its rank relative to its side
+    pub passed: [Score; 8],
+    /// Additional bonus for a passed pawn without any piece on its way to the promotion square
+    pub passed_free_path: [Score; 8],
+
+    // Mobility indexed by the number of reachable squares
+    pub knight_mobility: [Score; 9],
+    pub bishop_mobility: [Score; 14],
+    pub rook_mobility: [Score; 15],
+    pub queen_mobility: [Score; 28],
+
+    /// Indexed by the attack units on the king zone
+    pub king_danger: [Score; 100],
+    /// Shield pawn on one of the files next to the king indexed by its distance to the king, the
+    /// last entry applies when there is no pawn up to two ranks in front of the king
+    pub pawn_shield: [Score; 3],
+    /// File next to the king without own pawns
+    pub semi_open_file: Score,
+    /// File next to the king without any pawns
+    pub open_file: Score,
+}
+
+const _: () = assert!(size_of::<EvalParams>().is_multiple_of(size_of::<Score>()));
+
+impl EvalParams {
+    /// Number of weights
+    pub const LEN: usize = size_of::<EvalParams>() / size_of::<Score>();
+
+    pub fn weights(&self) -> &[Score] {
+        // SAFETY: the struct is `repr(C)` and only consists of scores, so it has the same layout as
+        // an array of `LEN` scores
+        unsafe { std::slice::from_raw_parts((self as *const Self).cast(), Self::LEN) }
+    }
+
+    pub fn weights_mut(&mut self) -> &mut [Score] {
+        // SAFETY: see `weights`
+        unsafe { std::slice::from_raw_parts_mut((self as *mut Self).cast(), Self::LEN) }
+    }
+
+    /// Index of `weight` in [`EvalParams::weights`], `weight` has to be a field of `self`
+    pub fn index_of(&self, weight: &Score) -> usize {
+        let offset = weight as *const Score as usize - self as *const Self as usize;
+        debug_assert!(offset < size_of::<Self>());
+
+        offset / size_of::<Score>()
+    }
+
+    /// The evaluation term the weight at `index` belongs to
+    pub fn term(index: usize) -> Term {
+        const fn start(offset: usize) -> usize {
+            offset / size_of::<Score>()
+        }
+
+        if index < start(offset_of!(EvalParams, psqt)) {
+            Term::Material
+        } else if index < start(offset_of!(EvalParams, doubled)) {
+            Term::PieceSquares
+        } else if index < start(offset_of!(EvalParams, knight_mobility)) {
+            Term::Pawns
+        } else if index < start(offset_of!(EvalParams, king_danger)) {
+            Term::Mobility
+        } else {
+            Term::KingSafety
+        }
+    }
+
+    /// Rust source defining these weights as `DEFAULT_PARAMS`, the contents of `params/default.rs`
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+
+        source.push_str(
+            "// Weights of the hand-crafted evaluation. This file is generated by the tuner, run\n\
+             // `cargo run --release -p tuner -- --help` for details.\n\n\
+             use super::EvalParams;\n\
+             use crate::evaluation::score::Score;\n\n\
+             const fn s(mg: i16, eg: i16) -> Score {\n    Score::new(mg, eg)\n}\n\n\
+             #[rustfmt::skip]\n\
+             pub const DEFAULT_PARAMS: EvalParams = EvalParams {\n",
+        );
+
+        write_array(&mut source, 1, "material", &self.material);
+        source.push_str("    psqt: [\n");
+        for table in &self.psqt {
+            write_array(&mut source, 2, "", table);
+        }
+        source.push_str("    ],\n");
+
+        write_scalar(&mut source, "doubled", self.doubled);
+        write_scalar(&mut source, "isolated", self.isolated);
+        write_scalar(&mut source, "backward", self.backward);
+        write_array(&mut source, 1, "passed", &self.passed);
+        write_array(&mut source, 1, "passed_free_path", &self.passed_free_path);
+
+        write_array(&mut source, 1, "knight_mobility", &self.knight_mobility);
+        write_array(&mut source, 1, "bishop_mobility", &self.bishop_mobility);
+        write_array(&mut source, 1, "rook_mobility", &self.rook_mobility);
+        write_array(&mut source, 1, "queen_mobility", &self.queen_mobility);
+
+        write_array(&mut source, 1, "king_danger", &self.king_danger);
+        write_array(&mut source, 1, "pawn_shield", &self.pawn_shield);
+        write_scalar(&mut source, "semi_open_file", self.semi_open_file);
+        write_scalar(&mut source, "open_file", self.open_file);
+
+        source.push_str("};\n");
+        source
+    }
+}
+
+fn format_score(score: Score) -> String {
+    format!("s({:4}, {:4})", score.mg(), score.eg())
+}
+
+fn write_scalar(source: &mut String, name: &str, score: Score) {
+    writeln!(source, "    {name}: {},", format_score(score)).unwrap();
+}
+
+/// Writes the scores eight per line, so piece square tables show one rank per line
+fn write_array(source: &mut String, indent: usize, name: &str, scores: &[Score]) {
+    let indent = "    ".repeat(indent);
+    let label = if name.is_empty() {
+        String::new()
+    } else {
+        format!("{name}: ")
+    };
+
+    writeln!(source, "{indent}{label}[").unwrap();
+    for row in scores.chunks(8) {
+        let row: Vec<_> = row.iter().map(|&score| format_score(score)).collect();
+        writeln!(source, "{indent}    {},", row.join(", ")).unwrap();
+    }
+    writeln!(source, "{indent}],").unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evaluation::params::{EvalParams, DEFAULT_PARAMS, PARAMS};
+    use crate::evaluation::score::Score;
+    use crate::evaluation::trace::Term;
+
+    #[test]
+    fn test_default_source_is_up_to_date() {
+        assert_eq!(
+            DEFAULT_PARAMS.to_source(),
+            include_str!("params/default.rs")
+        );
+    }
+
+    #[test]
+    fn test_weight_indices() {
+        assert_eq!(PARAMS.index_of(&PARAMS.material[0]), 0);
+        assert_eq!(PARAMS.index_of(&PARAMS.psqt[1][0]), 6 + 64);
+        assert_eq!(PARAMS.index_of(&PARAMS.open_file), EvalParams::LEN - 1);
+
+        assert_eq!(PARAMS.weights()[6 + 64 * 6], PARAMS.doubled);
+
+        let term = |weight| EvalParams::term(PARAMS.index_of(weight));
+        assert_eq!(term(&PARAMS.material[5]), Term::Material);
+        assert_eq!(term(&PARAMS.psqt[5][63]), Term::PieceSquares);
+        assert_eq!(term(&PARAMS.doubled), Term::Pawns);
+        assert_eq!(term(&PARAMS.passed_free_path[7]), Term::Pawns);
+        assert_eq!(term(&PARAMS.knight_mobility[0]), Term::Mobility);
+        assert_eq!(term(&PARAMS.queen_mobility[27]), Term::Mobility);
+        assert_eq!(term(&PARAMS.king_danger[0]), Term::KingSafety);
+        assert_eq!(term(&PARAMS.open_file), Term::KingSafety);
+
+        let mut params = DEFAULT_PARAMS.clone();
+        params.weights_mut()[EvalParams::LEN - 1] = Score::new(1, 2);
+        assert_eq!(params.open_file, Score::new(1, 2));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::board::Board;
+use crate::types::bitboard::BitBoard;
+use crate::types::color::{Color, ALL_COLORS};
+use crate::types::piece::PieceType;
+use crate::types::square::Square;
+
+use super::params::PARAMS;
+use super::score::Score;
+use super::trace::{NoTrace, Tracer};
+
+/// Number of entries of the pawn hash table, must be a power of two
+const PAWN_TABLE_SIZE: usize = 1 << 13;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PawnEntry {
+    key: u64,
+    /// Pawn structure score from white's point of view
+    score: Score,
+    /// Passed pawns of both sides
+    passed: BitBoard,
+}
+
+/// Caches the pawn structure evaluation by the pawn key of the board. The table is owned by a
+/// single search thread, so unlike the transposition table no synchronisation is needed.
+pub struct PawnTable {
+    entries: Box<[PawnEntry]>,
+}
+
+impl PawnTable {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![PawnEntry::default(); PAWN_TABLE_SIZE].into_boxed_slice(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(PawnEntry::default());
+    }
+
+    fn probe(&mut self, board: &Board) -> PawnEntry {
+        let key = board.pawn_key();
+        let entry = &mut self.entries[key as usize & (PAWN_TABLE_SIZE - 1)];
+
+        // the empty table matches pawnless positions, which evaluate to zero anyway
+        if entry.key != key {
+            *entry = evaluate_structure(board, &mut NoTrace);
+        }
+
+        *entry
+    }
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pawn structure evaluation from white's point of view
+pub fn evaluate<T: Tracer>(board: &Board, table: &mut PawnTable, tracer: &mut T) -> Score {
+    // a cached entry does not tell the contribution of each side
+    let entry = if T::ENABLED {
+        evaluate_structure(board, tracer)
+    } else {
+        table.probe(board)
+    };
+    let mut score = entry.score;
+
+    // the path of a passed pawn also depends on the other pieces and can not be cached
+    for color in ALL_COLORS {
+        let sign = sign(color);
+
+        for square in (entry.passed & board.occupancies(color)).iter() {
+            if (front_span(color, square) & board.combined()).is_empty() {
+                let bonus = &PARAMS.passed_free_path[relative_rank(color, square)];
+                score += tracer.apply(bonus, color) * sign;
+            }
+        }
+    }
+
+    score
+}
+
+fn evaluate_structure<T: Tracer>(board: &Board, tracer: &mut T) -> PawnEntry {
+    let pawns = board.pieces(PieceType::Pawn);
+
+    let mut score = Score::ZERO;
+    let mut passed = BitBoard::EMPTY;
+
+    for color in ALL_COLORS {
+        let mut structure = Score::ZERO;
+        let own = pawns & board.occupancies(color);
+        let enemy = pawns & board.occupancies(!color);
+        let enemy_attacks = pawn_attacks(enemy, !color);
+
+        for square in own.iter() {
+            let file = BitBoard::mask_file(square.file());
+            let adjacent = adjacent_files(file);
+            let front = front_span(color, square);
+
+            if !(own & front).is_empty() {
+                structure += tracer.apply(&PARAMS.doubled, color);
+            }
+
+            if (own & adjacent).is_empty() {
+                structure += tracer.apply(&PARAMS.isolated, color);
+            } else if (own & adjacent & !ranks_in_front(color, square)).is_empty() {
+                // no own pawn can ever defend it and the square in front is controlled by the
+                // opponent, so it can not advance either
+                let stop = square
+                    .forward(color)
+                    .map_or(BitBoard::EMPTY, BitBoard::from_square);
+                if !(stop & enemy_attacks).is_empty() {
+                    structure += tracer.apply(&PARAMS.backward, color);
+                }
+            }
+
+            let passed_mask = (file | adjacent) & ranks_in_front(color, square);
+            if (enemy & passed_mask).is_empty() && (own & front).is_empty() {
+                passed |= square;
+                structure += tracer.apply(&PARAMS.passed[relative_rank(color, square)], color);
+            }
+        }
+
+        score += structure * sign(color);
+    }
+
+    PawnEntry {
+        key: board.pawn_key(),
+        score,
+        passed,
+    }
+}
+
+const fn sign(color: Color) -> i32 {
+    match color {
+        Color::White => 1,
+        Color::Black => -1,
+    }
+}
+
+fn relative_rank(color: Color, square: Square) -> usize {
+    match color {
+        Color::White => square.rank() as usize,
+        Color::Black => 7 - square.rank() as usize,
+    }
+}
+
+fn adjacent_files(file: BitBoard) -> BitBoard {
+    (file.shift(1) & BitBoard::NOT_A_FILE) | (file.shift(-1) & BitBoard::NOT_H_FILE)
+}
+
+/// All squares on the ranks in front of `square` from the point of view of `color`
+pub(super) fn ranks_in_front(color: Color, square: Square) -> BitBoard {
+    let rank = square.rank() as usize;
+    let ranks = match color {
+        Color::White => &BitBoard::ALL_RANKS[rank + 1..],
+        Color::Black => &BitBoard::ALL_RANKS[..rank],
+    };
+
+    ranks
+        .iter()
+        .fold(BitBoard::EMPTY, |mask, &rank| mask | rank)
+}
+
+/// The squares in front of `square` on the same file
+fn front_span(color: Color, square: Square) -> BitBoard {
+    BitBoard::mask_file(square.file()) & ranks_in_front(color, square)
+}
+
+/// All squares attacked by `pawns` of `color`
+pub(super) fn pawn_attacks(pawns: BitBoard, color: Color) -> BitBoard {
+    let (left, right) = match color {
+        Color::White => (7, 9),
+        Color::Black => (-9, -7),
+    };
+
+    (pawns & BitBoard::NOT_A_FILE).shift(left) | (pawns & BitBoard::NOT_H_FILE).shift(right)
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::evaluation::params::PARAMS;
+    use crate::evaluation::pawns::{evaluate, evaluate_structure, PawnTable};
+    use crate::evaluation::score::Score;
+    use crate::evaluation::trace::NoTrace;
+    use crate::types::bitboard::BitBoard;
+    use crate::types::square::Square;
+
+    #[test]
+    fn test_structure_terms() {
+        // white: doubled and isolated pawns on the c-file, a passed pawn on h5
+        // black: backward pawn on d6
+        let board = Board::from_str("4k3/8/3p4/2P1p2P/4P3/2P5/8/4K3 w - - 0 1").unwrap();
+        let entry = evaluate_structure(&board, &mut NoTrace);
+
+        assert_eq!(entry.passed, BitBoard::from_square(Square::H5));
+        assert_eq!(
+            entry.score,
+            PARAMS.doubled + PARAMS.isolated * 4 + PARAMS.passed[4] - PARAMS.backward
+        );
+    }
+
+    #[test]
+    fn test_passed_pawn_path() {
+        let mut table = PawnTable::new();
+
+        let free = Board::from_str("4k3/8/8/1P6/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate(&free, &mut table, &mut NoTrace),
+            PARAMS.passed[4] + PARAMS.passed_free_path[4] + PARAMS.isolated
+        );
+
+        let blocked = Board::from_str("1n2k3/8/8/1P6/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate(&blocked, &mut table, &mut NoTrace),
+            PARAMS.passed[4] + PARAMS.isolated
+        );
+    }
+
+    #[test]
+    fn test_symmetric_and_cached() {
+        let mut table = PawnTable::new();
+
+        let white = Board::from_str("4k3/pp3p2/4p1p1/3P4/1P6/P4PP1/7P/4K3 w - - 0 1").unwrap();
+        let black = Board::from_str("4k3/7p/p4pp1/1p6/3p4/4P1P1/PP3P2/4K3 b - - 0 1").unwrap();
+
+        let score = evaluate(&white, &mut table, &mut NoTrace);
+        assert_ne!(score, Score::ZERO);
+        assert_eq!(evaluate(&black, &mut table, &mut NoTrace), -score);
+
+        // the second probe is answered from the table
+        assert_eq!(evaluate(&white, &mut table, &mut NoTrace), score);
+
+        table.clear();
+        assert!(table.entries.iter().all(|entry| entry.key == 0));
+        assert_eq!(evaluate(&white, &mut table, &mut NoTrace), score);
+    }
+}
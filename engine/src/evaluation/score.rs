@@ -0,0 +1,108 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// A middlegame and an endgame value packed into one integer, such that both are updated with a
+/// single addition. The endgame value is stored in the upper 16 bits and the middlegame value in
+/// the lower 16 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct Score(i32);
+
+impl Score {
+    pub const ZERO: Score = Score(0);
+
+    pub const fn new(mg: i16, eg: i16) -> Self {
+        Score(((eg as i32) << 16) + mg as i32)
+    }
+
+    pub const fn mg(self) -> i16 {
+        self.0 as i16
+    }
+
+    pub const fn eg(self) -> i16 {
+        // round up to undo the borrow of a negative middlegame value
+        ((self.0 + 0x8000) >> 16) as i16
+    }
+
+    /// Interpolates between the middlegame and the endgame value, `phase` goes from 0 in the
+    /// endgame to `max_phase` in the opening
+    pub const fn taper(self, phase: i32, max_phase: i32) -> i32 {
+        (self.mg() as i32 * phase + self.eg() as i32 * (max_phase - phase)) / max_phase
+    }
+}
+
+impl Add for Score {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Score(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Score {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Score(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Score {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Score(-self.0)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Self;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        Score(self.0 * rhs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evaluation::score::Score;
+
+    #[test]
+    fn test_packing() {
+        for (mg, eg) in [
+            (0, 0),
+            (5, -7),
+            (-5, 7),
+            (-300, -1),
+            (1200, 32000),
+            (-32000, -32000),
+        ] {
+            let score = Score::new(mg, eg);
+            assert_eq!((score.mg(), score.eg()), (mg, eg));
+        }
+
+        let sum = Score::new(-10, 20) + Score::new(3, -50) - Score::new(-1, 1);
+        assert_eq!((sum.mg(), sum.eg()), (-6, -31));
+        assert_eq!(-sum, Score::new(6, 31));
+        assert_eq!(Score::new(-4, 3) * 3, Score::new(-12, 9));
+    }
+
+    #[test]
+    fn test_taper() {
+        let score = Score::new(100, -20);
+        assert_eq!(score.taper(24, 24), 100);
+        assert_eq!(score.taper(0, 24), -20);
+        assert_eq!(score.taper(12, 24), 40);
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use crate::types::color::{Color, PerColor};
+
+use super::endgame::NORMAL_SCALE;
+use super::hce::{taper, MAX_PHASE};
+use super::params::{EvalParams, PARAMS};
+use super::score::Score;
+
+/// Terms of the hand-crafted evaluation that are reported separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    Material,
+    PieceSquares,
+    Pawns,
+    Mobility,
+    KingSafety,
+}
+
+impl Term {
+    pub const ALL: [Term; 5] = [
+        Term::Material,
+        Term::PieceSquares,
+        Term::Pawns,
+        Term::Mobility,
+        Term::KingSafety,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Term::Material => "Material",
+            Term::PieceSquares => "Piece squares",
+            Term::Pawns => "Pawns",
+            Term::Mobility => "Mobility",
+            Term::KingSafety => "King safety",
+        }
+    }
+}
+
+/// Receives every weight applied by the evaluation. The evaluation is generic over the tracer,
+/// so the work needed only to explain the evaluation is compiled out of the search.
+pub trait Tracer {
+    const ENABLED: bool;
+
+    /// Records that `weight`, a field of [`PARAMS`], was applied once for `color`
+    fn record(&mut self, weight: &'static Score, color: Color);
+
+    /// Records `weight` and returns it
+    #[inline(always)]
+    fn apply(&mut self, weight: &'static Score, color: Color) -> Score {
+        self.record(weight, color);
+        *weight
+    }
+}
+
+/// Tracer used during the search, it records nothing
+pub struct NoTrace;
+
+impl Tracer for NoTrace {
+    const ENABLED: bool = false;
+
+    #[inline(always)]
+    fn record(&mut self, _weight: &'static Score, _color: Color) {}
+}
+
+/// Breakdown of the hand-crafted evaluation by term and color
+#[derive(Debug, Default)]
+pub struct Trace {
+    terms: [PerColor<Score>; Term::ALL.len()],
+    pub(super) phase: i32,
+    pub(super) scale: i32,
+    /// Result of the specialised evaluation if the position is a known endgame
+    pub(super) endgame: Option<i32>,
+}
+
+impl Tracer for Trace {
+    const ENABLED: bool = true;
+
+    fn record(&mut self, weight: &'static Score, color: Color) {
+        let term = EvalParams::term(PARAMS.index_of(weight));
+        self.terms[term as usize][color] += *weight;
+    }
+}
+
+impl Trace {
+    /// Contribution of `term` from the point of view of `color`
+    pub fn term(&self, term: Term, color: Color) -> Score {
+        self.terms[term as usize][color]
+    }
+
+    /// Sum of all terms from white's point of view
+    pub fn total(&self) -> Score {
+        Term::ALL.into_iter().fold(Score::ZERO, |total, term| {
+            total + self.term(term, Color::White) - self.term(term, Color::Black)
+        })
+    }
+
+    pub fn phase(&self) -> i32 {
+        self.phase
+    }
+
+    /// Scale factor of the endgame score out of [`NORMAL_SCALE`]
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    pub fn endgame(&self) -> Option<i32> {
+        self.endgame
+    }
+
+    /// The evaluation in centipawns from white's point of view
+    pub fn evaluation(&self) -> i32 {
+        self.endgame
+            .unwrap_or_else(|| taper(self.total(), self.phase, self.scale))
+    }
+}
+
+const SEPARATOR: &str = "---------------+-------------+-------------+-------------+";
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fn cells(f: &mut Formatter<'_>, score: Score) -> fmt::Result {
+            write!(f, " {:>5} {:>5} |", score.mg(), score.eg())
+        }
+
+        writeln!(
+            f,
+            "      Term     |    White    |    Black    |    Total    |"
+        )?;
+        writeln!(
+            f,
+            "               |   MG    EG  |   MG    EG  |   MG    EG  |"
+        )?;
+        writeln!(f, "{SEPARATOR}")?;
+
+        for term in Term::ALL {
+            let (white, black) = (self.term(term, Color::White), self.term(term, Color::Black));
+
+            write!(f, " {:>13} |", term.name())?;
+            cells(f, white)?;
+            cells(f, black)?;
+            cells(f, white - black)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "{SEPARATOR}")?;
+        write!(f, " {:>13} |             |             |", "Total")?;
+        cells(f, self.total())?;
+        writeln!(f)?;
+        writeln!(f)?;
+
+        writeln!(f, "Phase: {} / {MAX_PHASE}", self.phase)?;
+        writeln!(f, "Endgame scale: {} / {NORMAL_SCALE}", self.scale)?;
+        if self.endgame.is_some() {
+            writeln!(
+                f,
+                "Known endgame, the terms are replaced by its specialised evaluation"
+            )?;
+        }
+        write!(
+            f,
+            "Classical evaluation: {:+.2} (white side)",
+            self.evaluation() as f64 / 100.0
+        )
+    }
+}
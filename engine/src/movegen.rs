@@ -9,11 +9,11 @@ use slider::generate_slider_moves;
 
 use crate::board::Board;
 use crate::movegen::attacks::{
-    get_bishop_attacks, get_king_attacks, get_knight_attacks, get_pawn_attacks, get_queen_attacks,
-    get_rook_attacks,
+    between, get_bishop_attacks, get_king_attacks, get_knight_attacks, get_pawn_attacks,
+    get_queen_attacks, get_rook_attacks, line,
 };
 use crate::types::bitboard::BitBoard;
-use crate::types::chess_move::Move;
+use crate::types::chess_move::{Move, MoveFlag};
 use crate::types::color::Color;
 use crate::types::piece::{PieceType, ALL_PIECES};
 use crate::types::square::Square;
@@ -58,6 +58,16 @@ pub fn generate_attack_bitboard(board: &Board, attacking_color: Color) -> BitBoa
 }
 
 pub fn generate_moves<const CAPTURE_ONLY: bool>(board: &Board) -> MoveList {
+    generate::<CAPTURE_ONLY, false>(board)
+}
+
+/// Generates only the moves that `generate_moves::<true>` leaves out, i.e. quiet moves, quiet
+/// promotions and castling
+pub fn generate_quiet_moves(board: &Board) -> MoveList {
+    generate::<false, true>(board)
+}
+
+fn generate<const CAPTURE_ONLY: bool, const QUIET_ONLY: bool>(board: &Board) -> MoveList {
     let mut move_list = MoveList::new();
 
     let checkers = board.checkers();
@@ -66,40 +76,164 @@ pub fn generate_moves<const CAPTURE_ONLY: bool>(board: &Board) -> MoveList {
             generate_quiet_pawn_moves::<false>(board, &mut move_list);
         }
 
-        generate_pawn_capture_moves::<false>(board, &mut move_list);
-        generate_en_passant_move::<false>(board, &mut move_list);
+        if !QUIET_ONLY {
+            generate_pawn_capture_moves::<false>(board, &mut move_list);
+            generate_en_passant_move::<false>(board, &mut move_list);
+        }
 
-        generate_knight_moves::<false, CAPTURE_ONLY>(board, &mut move_list);
+        generate_knight_moves::<false, CAPTURE_ONLY, QUIET_ONLY>(board, &mut move_list);
 
-        generate_slider_moves::<false, CAPTURE_ONLY>(board, &mut move_list);
+        generate_slider_moves::<false, CAPTURE_ONLY, QUIET_ONLY>(board, &mut move_list);
 
         if !CAPTURE_ONLY {
             generate_castling_moves::<false>(board, &mut move_list);
         }
 
-        generate_king_moves::<false, CAPTURE_ONLY>(board, &mut move_list);
+        generate_king_moves::<false, CAPTURE_ONLY, QUIET_ONLY>(board, &mut move_list);
     } else if checkers.count() == 1 {
         // a single check can be evaded by capturing the checker, blocking the check or by moving the king
 
-        generate_quiet_pawn_moves::<true>(board, &mut move_list);
-        generate_pawn_capture_moves::<true>(board, &mut move_list);
-        generate_en_passant_move::<true>(board, &mut move_list);
+        if !CAPTURE_ONLY {
+            generate_quiet_pawn_moves::<true>(board, &mut move_list);
+        }
+
+        if !QUIET_ONLY {
+            generate_pawn_capture_moves::<true>(board, &mut move_list);
+            generate_en_passant_move::<true>(board, &mut move_list);
+        }
 
-        generate_knight_moves::<true, CAPTURE_ONLY>(board, &mut move_list);
+        generate_knight_moves::<true, CAPTURE_ONLY, QUIET_ONLY>(board, &mut move_list);
 
-        generate_slider_moves::<true, CAPTURE_ONLY>(board, &mut move_list);
+        generate_slider_moves::<true, CAPTURE_ONLY, QUIET_ONLY>(board, &mut move_list);
 
         // castling is not allowed when the king is in check
-        generate_king_moves::<true, CAPTURE_ONLY>(board, &mut move_list);
+        generate_king_moves::<true, CAPTURE_ONLY, QUIET_ONLY>(board, &mut move_list);
     } else {
         // double and more checkers
         // only the king can move
-        generate_king_moves::<true, CAPTURE_ONLY>(board, &mut move_list);
+        generate_king_moves::<true, CAPTURE_ONLY, QUIET_ONLY>(board, &mut move_list);
     }
 
     move_list
 }
 
+/// Checks whether a move that was not generated for the current position, e.g. a move from the
+/// transposition table or a killer slot, could be played by the side to move while ignoring
+/// whether it leaves the own king in check
+pub fn is_pseudo_legal(board: &Board, mov: Move) -> bool {
+    let side_to_move = board.side_to_move();
+    let (from, to) = (mov.from(), mov.to());
+
+    let Some(piece) = board.piece_at(from) else {
+        return false;
+    };
+
+    if piece.color() != side_to_move || from == to {
+        return false;
+    }
+
+    // castling and en passant have enough special conditions to simply ask the generators
+    match mov.flag() {
+        MoveFlag::Castling => {
+            let mut move_list = MoveList::new();
+            if piece.ty() == PieceType::King && board.checkers().is_empty() {
+                generate_castling_moves::<false>(board, &mut move_list);
+            }
+            return move_list.contains(&mov);
+        }
+        MoveFlag::EnPassant => {
+            let mut move_list = MoveList::new();
+            generate_en_passant_move::<false>(board, &mut move_list);
+            return move_list.contains(&mov);
+        }
+        _ => {}
+    }
+
+    match board.piece_at(to) {
+        Some(captured) if !mov.is_capture() || captured.color() == side_to_move => return false,
+        None if mov.is_capture() => return false,
+        _ => {}
+    }
+
+    let combined = board.combined();
+
+    let reachable = match piece.ty() {
+        PieceType::Pawn => {
+            let on_promotion_rank = to.rank() == (!side_to_move).backrank();
+            if on_promotion_rank != mov.promotion().is_some() {
+                return false;
+            }
+
+            let single_push = from.forward(side_to_move);
+
+            if mov.is_capture() {
+                get_pawn_attacks(from, side_to_move)
+            } else if mov.flag() == MoveFlag::DoublePawnPush {
+                match single_push {
+                    Some(square)
+                        if !combined.contains(square)
+                            && to.rank() == side_to_move.double_pawn_push_rank() =>
+                    {
+                        square
+                            .forward(side_to_move)
+                            .map_or(BitBoard::EMPTY, BitBoard::from_square)
+                    }
+                    _ => BitBoard::EMPTY,
+                }
+            } else {
+                single_push.map_or(BitBoard::EMPTY, BitBoard::from_square)
+            }
+        }
+        _ if !matches!(mov.flag(), MoveFlag::Normal | MoveFlag::Capture) => return false,
+        PieceType::Knight => get_knight_attacks(from),
+        PieceType::Bishop => get_bishop_attacks(from, combined),
+        PieceType::Rook => get_rook_attacks(from, combined),
+        PieceType::Queen => get_queen_attacks(from, combined),
+        PieceType::King => get_king_attacks(from),
+    };
+
+    reachable.contains(to)
+}
+
+/// Checks whether a move that was not generated for the current position is legal, i.e. it is
+/// pseudo legal and does not leave the own king in check
+pub fn is_legal(board: &Board, mov: Move) -> bool {
+    if !is_pseudo_legal(board, mov) {
+        return false;
+    }
+
+    let checkers = board.checkers();
+
+    match mov.flag() {
+        // the generators already verified these
+        MoveFlag::Castling => return true,
+        MoveFlag::EnPassant => return checkers.count() < 2,
+        _ => {}
+    }
+
+    let side_to_move = board.side_to_move();
+    let king_square = (board.pieces(PieceType::King) & board.occupancies(side_to_move)).bit_scan();
+
+    if mov.from() == king_square {
+        return !generate_attack_bitboard(board, !side_to_move).contains(mov.to());
+    }
+
+    match checkers.count() {
+        0 => {}
+        1 => {
+            // capture the checker or block the check
+            let evasions = checkers | between(king_square, checkers.bit_scan());
+            if !evasions.contains(mov.to()) {
+                return false;
+            }
+        }
+        _ => return false,
+    }
+
+    // pinned pieces may only move along the pin
+    !board.pinned().contains(mov.from()) || line(king_square, mov.from()).contains(mov.to())
+}
+
 pub fn is_square_attacked(board: &Board, attacked_square: Square, attacking_side: Color) -> bool {
     // attacked by pawns?
     if (get_pawn_attacks(attacked_square, !attacking_side)
@@ -204,12 +338,41 @@ mod test {
 
     use crate::board::Board;
     use crate::movegen::{
-        build_attacked_bitboard, generate_attack_bitboard, generate_moves, is_square_attacked,
+        build_attacked_bitboard, generate_attack_bitboard, generate_moves, generate_quiet_moves,
+        is_legal, is_square_attacked,
     };
     use crate::types::bitboard::BitBoard;
+    use crate::types::chess_move::{Move, MoveFlag};
     use crate::types::color::Color;
     use crate::types::square::Square;
 
+    const LEGALITY_TEST_POSITIONS: [&str; 8] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "4k3/8/8/8/1b6/8/3P4/4K2R w K - 0 1",
+        "4k3/8/8/8/8/5n2/8/R3K2R w KQ - 0 1",
+    ];
+
+    const ALL_FLAGS: [MoveFlag; 13] = [
+        MoveFlag::Normal,
+        MoveFlag::DoublePawnPush,
+        MoveFlag::Castling,
+        MoveFlag::Capture,
+        MoveFlag::EnPassant,
+        MoveFlag::KnightPromotion,
+        MoveFlag::BishopPromotion,
+        MoveFlag::RookPromotion,
+        MoveFlag::QueenPromotion,
+        MoveFlag::KnightPromotionCapture,
+        MoveFlag::BishopPromotionCapture,
+        MoveFlag::RookPromotionCapture,
+        MoveFlag::QueenPromotionCapture,
+    ];
+
     #[test]
     fn test_is_square_attacked_pawn_attack() {
         let board = Board::from_str("k7/8/8/3p4/8/8/8/K7 w - - 0 1").unwrap();
@@ -337,4 +500,39 @@ mod test {
 
         assert_eq!(moves.len(), 4);
     }
+
+    #[test]
+    fn quiets_and_captures_partition_all_moves() {
+        for fen in LEGALITY_TEST_POSITIONS {
+            let board = Board::from_str(fen).unwrap();
+
+            let all = generate_moves::<false>(&board);
+            let captures = generate_moves::<true>(&board);
+            let quiets = generate_quiet_moves(&board);
+
+            assert_eq!(all.len(), captures.len() + quiets.len(), "{fen}");
+            assert!(captures.iter().all(|mov| mov.is_capture()), "{fen}");
+            assert!(quiets.iter().all(|mov| !mov.is_capture()), "{fen}");
+            assert!(all
+                .iter()
+                .all(|mov| captures.contains(mov) || quiets.contains(mov)));
+        }
+    }
+
+    #[test]
+    fn is_legal_matches_generated_moves() {
+        for fen in LEGALITY_TEST_POSITIONS {
+            let board = Board::from_str(fen).unwrap();
+            let moves = generate_moves::<false>(&board);
+
+            for from in 0..64 {
+                for to in 0..64 {
+                    for flag in ALL_FLAGS {
+                        let mov = Move::new(Square::from_index(from), Square::from_index(to), flag);
+                        assert_eq!(is_legal(&board, mov), moves.contains(&mov), "{fen} {mov:?}");
+                    }
+                }
+            }
+        }
+    }
 }
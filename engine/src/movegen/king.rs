@@ -1,10 +1,11 @@
 use crate::board::Board;
 use crate::movegen::attacks::get_king_attacks;
 use crate::movegen::{generate_attack_bitboard, MoveList};
+use crate::types::bitboard::BitBoard;
 use crate::types::chess_move::{Move, MoveFlag};
 use crate::types::piece::PieceType;
 
-pub fn generate_king_moves<const CHECK: bool, const CAPTURE_ONLY: bool>(
+pub fn generate_king_moves<const CHECK: bool, const CAPTURE_ONLY: bool, const QUIET_ONLY: bool>(
     board: &Board,
     move_list: &mut MoveList,
 ) {
@@ -20,6 +21,9 @@ pub fn generate_king_moves<const CHECK: bool, const CAPTURE_ONLY: bool>(
 
     // limit captures to the opponent pieces
     capture_mask &= board.occupancies(!side_to_move);
+    if QUIET_ONLY {
+        capture_mask = BitBoard::EMPTY;
+    }
     // avoid opponent pieces on quiet moves
     push_mask &= !board.occupancies(!side_to_move);
 
@@ -52,7 +56,7 @@ mod test {
     fn test_xray_attack() {
         let board = Board::from_str("8/4k3/8/8/8/4R3/8/K7 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_king_moves::<true, false>(&board, &mut move_list);
+        generate_king_moves::<true, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
 
         assert_eq!(move_list.len(), 6);
@@ -72,7 +76,7 @@ mod test {
     fn test_forced_capture() {
         let board = Board::from_str("6Qk/8/8/8/8/2q5/8/1K6 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_king_moves::<true, false>(&board, &mut move_list);
+        generate_king_moves::<true, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
 
         assert_eq!(move_list.len(), 1);
@@ -83,7 +87,7 @@ mod test {
     fn test_checkmate() {
         let board = Board::from_str("3Q2k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_king_moves::<true, false>(&board, &mut move_list);
+        generate_king_moves::<true, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
 
         assert_eq!(move_list.len(), 0);
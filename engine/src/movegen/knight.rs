@@ -5,7 +5,11 @@ use crate::types::bitboard::BitBoard;
 use crate::types::chess_move::{Move, MoveFlag};
 use crate::types::piece::PieceType;
 
-pub fn generate_knight_moves<const CHECK: bool, const CAPTURE_ONLY: bool>(
+pub fn generate_knight_moves<
+    const CHECK: bool,
+    const CAPTURE_ONLY: bool,
+    const QUIET_ONLY: bool,
+>(
     board: &Board,
     move_list: &mut MoveList,
 ) {
@@ -30,6 +34,9 @@ pub fn generate_knight_moves<const CHECK: bool, const CAPTURE_ONLY: bool>(
 
     // limit captures to the opponent pieces
     capture_mask &= board.occupancies(!side_to_move);
+    if QUIET_ONLY {
+        capture_mask = BitBoard::EMPTY;
+    }
     // avoid opponent pieces on quiet moves
     push_mask &= !board.occupancies(!side_to_move);
 
@@ -65,7 +72,7 @@ mod test {
     fn test_check_evasion() {
         let board = Board::from_str("4k2n/8/6n1/4R3/8/8/8/K7 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_knight_moves::<true, false>(&board, &mut move_list);
+        generate_knight_moves::<true, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
 
         assert_eq!(move_list.len(), 2);
@@ -77,7 +84,7 @@ mod test {
     fn test_self_capture_prevention() {
         let board = Board::from_str("4k2n/8/6n1/8/8/8/8/K7 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_knight_moves::<false, false>(&board, &mut move_list);
+        generate_knight_moves::<false, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
         assert!(!move_list.contains(&Move::new(G6, H8, MoveFlag::Capture)));
         assert!(!move_list.contains(&Move::new(H8, G6, MoveFlag::Capture)));
@@ -87,7 +94,7 @@ mod test {
     fn test_pinned_knight_can_not_move() {
         let board = Board::from_str("4k3/8/4n3/8/8/8/8/K3R3 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_knight_moves::<false, false>(&board, &mut move_list);
+        generate_knight_moves::<false, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
         assert_eq!(move_list.len(), 0);
     }
@@ -96,7 +103,7 @@ mod test {
     fn test_capture_empty_square() {
         let board = Board::from_str("3pkp2/2p3p1/4n3/2p3p1/3p4/8/8/K7 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_knight_moves::<false, false>(&board, &mut move_list);
+        generate_knight_moves::<false, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
         assert_eq!(move_list.len(), 1);
         assert!(move_list.contains(&Move::new(E6, F4, MoveFlag::Normal)));
@@ -106,7 +113,7 @@ mod test {
     fn test_capture_marked_as_quiet() {
         let board = Board::from_str("3BkB2/2P3P1/4n3/2P3P1/3P4/8/8/K7 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_knight_moves::<false, false>(&board, &mut move_list);
+        generate_knight_moves::<false, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
 
         assert_eq!(move_list.len(), 8);
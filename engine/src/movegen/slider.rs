@@ -5,7 +5,11 @@ use crate::types::bitboard::BitBoard;
 use crate::types::chess_move::{Move, MoveFlag};
 use crate::types::piece::PieceType;
 
-pub fn generate_slider_moves<const CHECK: bool, const CAPTURE_ONLY: bool>(
+pub fn generate_slider_moves<
+    const CHECK: bool,
+    const CAPTURE_ONLY: bool,
+    const QUIET_ONLY: bool,
+>(
     board: &Board,
     move_list: &mut MoveList,
 ) {
@@ -29,6 +33,9 @@ pub fn generate_slider_moves<const CHECK: bool, const CAPTURE_ONLY: bool>(
 
     // limit captures to the opponent pieces
     capture_mask &= board.occupancies(!side_to_move);
+    if QUIET_ONLY {
+        capture_mask = BitBoard::EMPTY;
+    }
     // avoid opponent pieces on quiet moves
     push_mask &= !board.occupancies(!side_to_move);
 
@@ -125,7 +132,7 @@ mod test {
     fn test_move_along_pin_ray() {
         let board = Board::from_str("4k3/8/7b/3P4/8/8/3B4/2K5 w - - 3 2").unwrap();
         let mut move_list = MoveList::new();
-        generate_slider_moves::<false, false>(&board, &mut move_list);
+        generate_slider_moves::<false, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
         assert_eq!(move_list.len(), 4);
 
@@ -142,7 +149,7 @@ mod test {
     fn test_pinned_bishop_captures() {
         let board = Board::from_str("8/2p5/3p4/KP5r/1R3b1k/6P1/4P3/8 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_slider_moves::<true, false>(&board, &mut move_list);
+        generate_slider_moves::<true, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
         assert_eq!(move_list.len(), 0);
     }
@@ -151,7 +158,7 @@ mod test {
     fn test_pinned_rook_captures() {
         let board = Board::from_str("8/2p5/3p4/KP5r/1R4rk/6P1/4P3/8 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_slider_moves::<true, false>(&board, &mut move_list);
+        generate_slider_moves::<true, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
         assert_eq!(move_list.len(), 0);
     }
@@ -160,7 +167,7 @@ mod test {
     fn test_pinned_queen_captures() {
         let board = Board::from_str("8/2p5/3p4/KP5r/1R4qk/6P1/4P3/8 b - - 0 1").unwrap();
         let mut move_list = MoveList::new();
-        generate_slider_moves::<true, false>(&board, &mut move_list);
+        generate_slider_moves::<true, false, false>(&board, &mut move_list);
         println!("{:#?}", move_list);
         assert_eq!(move_list.len(), 0);
     }
@@ -1,5 +1,23 @@
+pub mod countermove;
+pub mod history;
+pub mod killers;
+pub mod picker;
+
+use crate::types::chess_move::Move;
+use crate::types::color::Color;
 use crate::types::piece::{PieceType, NUM_PIECES};
 
+use countermove::CounterMoveTable;
+use history::{history_bonus, HistoryTable, MAX_HISTORY};
+use killers::KillerMoves;
+
+/// Ordering scores of the killer moves and the counter move. They are tried before all other
+/// quiet moves.
+pub const KILLER_SCORES: [i32; 2] = [400_000, 300_000];
+pub const COUNTER_MOVE_SCORE: i32 = 200_000;
+
+const _: () = assert!(COUNTER_MOVE_SCORE > MAX_HISTORY);
+
 #[rustfmt::skip]
 const MVV_LVA: [[u8; NUM_PIECES]; NUM_PIECES] = [
     [15, 14, 13, 12, 11, 10], // victim Pawn
@@ -14,10 +32,71 @@ pub(crate) fn mmv_lva(src_piece: PieceType, dst_piece: PieceType) -> i32 {
     MVV_LVA[dst_piece as usize][src_piece as usize] as i32
 }
 
+/// Per-thread move ordering state that persists between searches
+#[derive(Debug, Clone, Default)]
+pub struct MoveOrdering {
+    pub killers: KillerMoves,
+    pub history: HistoryTable,
+    pub counter_moves: CounterMoveTable,
+}
+
+impl MoveOrdering {
+    pub fn clear(&mut self) {
+        self.killers.clear();
+        self.history.clear();
+        self.counter_moves.clear();
+    }
+
+    /// Ordering score of a quiet move
+    pub fn quiet_score(&self, color: Color, ply: usize, previous: Option<Move>, mov: Move) -> i32 {
+        let [first_killer, second_killer] = self.killers.get(ply);
+
+        if mov == first_killer {
+            KILLER_SCORES[0]
+        } else if mov == second_killer {
+            KILLER_SCORES[1]
+        } else if previous.is_some_and(|previous| self.counter_moves.get(previous) == mov) {
+            COUNTER_MOVE_SCORE
+        } else {
+            self.history.get(color, mov)
+        }
+    }
+
+    /// Rewards a quiet move that caused a beta cutoff and penalizes the quiet moves that were
+    /// searched before it without causing one
+    pub fn update_quiet_cutoff(
+        &mut self,
+        color: Color,
+        ply: usize,
+        depth: u8,
+        previous: Option<Move>,
+        mov: Move,
+        searched_quiets: &[Move],
+    ) {
+        let bonus = history_bonus(depth);
+
+        self.killers.store(ply, mov);
+        self.history.update(color, mov, bonus);
+
+        for &quiet in searched_quiets {
+            if quiet != mov {
+                self.history.update(color, quiet, -bonus);
+            }
+        }
+
+        if let Some(previous) = previous {
+            self.counter_moves.store(previous, mov);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::moveord::mmv_lva;
+    use crate::moveord::{mmv_lva, MoveOrdering, COUNTER_MOVE_SCORE, KILLER_SCORES};
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::color::Color;
     use crate::types::piece::{PieceType, ALL_PIECES};
+    use crate::types::square::Square;
 
     #[test]
     fn test() {
@@ -36,4 +115,32 @@ mod test {
             println!("{:?} takes {:?}: {}", src_piece, dst_piece, score);
         }
     }
+
+    #[test]
+    fn test_quiet_cutoff_update() {
+        let previous = Move::new(Square::E7, Square::E5, MoveFlag::DoublePawnPush);
+        let cutoff = Move::new(Square::G1, Square::F3, MoveFlag::Normal);
+        let searched = Move::new(Square::A2, Square::A3, MoveFlag::Normal);
+
+        let mut ordering = MoveOrdering::default();
+        ordering.update_quiet_cutoff(Color::White, 4, 6, Some(previous), cutoff, &[searched]);
+
+        assert_eq!(
+            ordering.quiet_score(Color::White, 4, Some(previous), cutoff),
+            KILLER_SCORES[0]
+        );
+        // at another ply the move is only known as the counter move
+        assert_eq!(
+            ordering.quiet_score(Color::White, 5, Some(previous), cutoff),
+            COUNTER_MOVE_SCORE
+        );
+        assert!(ordering.quiet_score(Color::White, 5, None, cutoff) > 0);
+        assert!(ordering.quiet_score(Color::White, 5, None, searched) < 0);
+
+        ordering.clear();
+        assert_eq!(
+            ordering.quiet_score(Color::White, 4, Some(previous), cutoff),
+            0
+        );
+    }
 }
@@ -0,0 +1,35 @@
+use crate::types::chess_move::Move;
+use crate::types::square::NUM_SQUARES;
+
+/// Quiet moves that refuted the previous move, indexed by the source and target square of the
+/// previous move
+#[derive(Debug, Clone)]
+pub struct CounterMoveTable {
+    inner: [[Move; NUM_SQUARES]; NUM_SQUARES],
+}
+
+impl CounterMoveTable {
+    pub fn new() -> Self {
+        Self {
+            inner: [[Move::NULL; NUM_SQUARES]; NUM_SQUARES],
+        }
+    }
+
+    pub fn get(&self, previous: Move) -> Move {
+        self.inner[previous.from() as usize][previous.to() as usize]
+    }
+
+    pub fn store(&mut self, previous: Move, mov: Move) {
+        self.inner[previous.from() as usize][previous.to() as usize] = mov;
+    }
+
+    pub fn clear(&mut self) {
+        self.inner = [[Move::NULL; NUM_SQUARES]; NUM_SQUARES];
+    }
+}
+
+impl Default for CounterMoveTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
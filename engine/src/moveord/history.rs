@@ -0,0 +1,76 @@
+use crate::types::chess_move::Move;
+use crate::types::color::{Color, NUM_COLORS};
+use crate::types::square::NUM_SQUARES;
+
+/// Upper bound of the absolute value of a history score
+pub const MAX_HISTORY: i32 = 16384;
+
+/// Butterfly history table indexed by color, source and target square
+#[derive(Debug, Clone)]
+pub struct HistoryTable {
+    inner: [[[i16; NUM_SQUARES]; NUM_SQUARES]; NUM_COLORS],
+}
+
+impl HistoryTable {
+    pub fn new() -> Self {
+        Self {
+            inner: [[[0; NUM_SQUARES]; NUM_SQUARES]; NUM_COLORS],
+        }
+    }
+
+    pub fn get(&self, color: Color, mov: Move) -> i32 {
+        self.inner[color as usize][mov.from() as usize][mov.to() as usize] as i32
+    }
+
+    /// Applies a bonus (or malus, if negative) to a move. The gravity term pulls the score back
+    /// towards zero the closer it gets to [`MAX_HISTORY`] so that the score stays bounded.
+    pub fn update(&mut self, color: Color, mov: Move, bonus: i32) {
+        let bonus = bonus.clamp(-MAX_HISTORY, MAX_HISTORY);
+        let entry = &mut self.inner[color as usize][mov.from() as usize][mov.to() as usize];
+
+        let value = *entry as i32;
+        *entry = (value + bonus - value * bonus.abs() / MAX_HISTORY) as i16;
+    }
+
+    pub fn clear(&mut self) {
+        self.inner = [[[0; NUM_SQUARES]; NUM_SQUARES]; NUM_COLORS];
+    }
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// History bonus for a quiet move that caused a beta cutoff at the given depth
+pub fn history_bonus(depth: u8) -> i32 {
+    (depth as i32 * depth as i32).min(1200)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::moveord::history::{HistoryTable, MAX_HISTORY};
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::color::Color;
+    use crate::types::square::Square;
+
+    #[test]
+    fn test_gravity_bounds_score() {
+        let mov = Move::new(Square::G1, Square::F3, MoveFlag::Normal);
+
+        let mut history = HistoryTable::new();
+        for _ in 0..10000 {
+            history.update(Color::White, mov, 1200);
+        }
+        assert!(history.get(Color::White, mov) <= MAX_HISTORY);
+        assert!(history.get(Color::White, mov) > 0);
+        assert_eq!(history.get(Color::Black, mov), 0);
+
+        for _ in 0..10000 {
+            history.update(Color::White, mov, -1200);
+        }
+        assert!(history.get(Color::White, mov) >= -MAX_HISTORY);
+        assert!(history.get(Color::White, mov) < 0);
+    }
+}
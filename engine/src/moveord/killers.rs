@@ -0,0 +1,69 @@
+use crate::pv_table::MAX_PLY;
+use crate::types::chess_move::Move;
+
+/// Two quiet moves per ply that recently caused a beta cutoff
+#[derive(Debug, Clone)]
+pub struct KillerMoves {
+    inner: [[Move; 2]; MAX_PLY],
+}
+
+impl KillerMoves {
+    pub fn new() -> Self {
+        Self {
+            inner: [[Move::NULL; 2]; MAX_PLY],
+        }
+    }
+
+    pub fn get(&self, ply: usize) -> [Move; 2] {
+        self.inner[ply]
+    }
+
+    pub fn contains(&self, ply: usize, mov: Move) -> bool {
+        self.inner[ply].contains(&mov)
+    }
+
+    pub fn store(&mut self, ply: usize, mov: Move) {
+        let killers = &mut self.inner[ply];
+        if killers[0] != mov {
+            killers[1] = killers[0];
+            killers[0] = mov;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.inner = [[Move::NULL; 2]; MAX_PLY];
+    }
+}
+
+impl Default for KillerMoves {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::moveord::killers::KillerMoves;
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::square::Square;
+
+    #[test]
+    fn test_store() {
+        let first = Move::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+        let second = Move::new(Square::G1, Square::F3, MoveFlag::Normal);
+        let third = Move::new(Square::B1, Square::C3, MoveFlag::Normal);
+
+        let mut killers = KillerMoves::new();
+        killers.store(3, first);
+        killers.store(3, first);
+        assert_eq!(killers.get(3), [first, Move::NULL]);
+
+        killers.store(3, second);
+        assert_eq!(killers.get(3), [second, first]);
+
+        killers.store(3, third);
+        assert_eq!(killers.get(3), [third, second]);
+        assert!(!killers.contains(3, first));
+        assert!(!killers.contains(2, third));
+    }
+}
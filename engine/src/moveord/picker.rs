@@ -0,0 +1,262 @@
+use crate::board::Board;
+use crate::movegen::{MoveList, OrderingList};
+use crate::moveord::{mmv_lva, MoveOrdering};
+use crate::types::chess_move::Move;
+use crate::types::piece::PieceType;
+use crate::types::promotion::Promotion;
+
+/// Ordering score of quiet queen promotions, tried before every other quiet move
+const QUEEN_PROMOTION_SCORE: i32 = 1_000_000;
+/// Ordering score of quiet under promotions, tried after every other quiet move
+const UNDER_PROMOTION_SCORE: i32 = -1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TTMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Hands out the legal moves of a position one at a time, best first.
+///
+/// The moves are produced in stages: the move from the transposition table, captures that do not
+/// lose material according to the static exchange evaluation, the killer moves, the remaining
+/// quiet moves ordered by their history and finally the losing captures. Each category is only
+/// generated once the previous ones are exhausted, so a cutoff by an early move saves generating
+/// and scoring the rest.
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: Move,
+    killers: [Move; 2],
+    ply: usize,
+    previous_move: Option<Move>,
+
+    moves: MoveList,
+    scores: OrderingList,
+    index: usize,
+    bad_captures: MoveList,
+}
+
+impl MovePicker {
+    pub fn new(
+        tt_move: Move,
+        ply: usize,
+        previous_move: Option<Move>,
+        ordering: &MoveOrdering,
+    ) -> Self {
+        Self {
+            stage: Stage::TTMove,
+            tt_move,
+            killers: ordering.killers.get(ply),
+            ply,
+            previous_move,
+
+            moves: MoveList::new(),
+            scores: OrderingList::new(),
+            index: 0,
+            bad_captures: MoveList::new(),
+        }
+    }
+
+    /// Returns the next legal move or `None` once all moves have been handed out
+    pub fn next(&mut self, board: &Board, ordering: &MoveOrdering) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TTMove => {
+                    self.stage = Stage::GenerateCaptures;
+
+                    // the move might come from a different position with the same hash key
+                    if self.tt_move != Move::NULL && board.is_legal(self.tt_move) {
+                        return Some(self.tt_move);
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.moves = board.generate_moves_captures_only();
+                    self.scores = self
+                        .moves
+                        .iter()
+                        .map(|&mov| capture_score(board, mov))
+                        .collect();
+                    self.index = 0;
+
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => match self.select_best() {
+                    Some(mov) if mov == self.tt_move => {}
+                    Some(mov) if !board.see(mov, 0) => self.bad_captures.push(mov),
+                    Some(mov) => return Some(mov),
+                    None => {
+                        self.index = 0;
+                        self.stage = Stage::Killers;
+                    }
+                },
+                Stage::Killers => {
+                    if self.index >= self.killers.len() {
+                        self.stage = Stage::GenerateQuiets;
+                        continue;
+                    }
+
+                    let killer = self.killers[self.index];
+                    self.index += 1;
+
+                    // killers are found in sibling positions and have to be validated
+                    if killer != Move::NULL
+                        && killer != self.tt_move
+                        && !killer.is_capture()
+                        && board.is_legal(killer)
+                    {
+                        return Some(killer);
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    self.moves = board.generate_moves_quiets_only();
+                    self.scores = self
+                        .moves
+                        .iter()
+                        .map(|&mov| quiet_score(board, ordering, self.ply, self.previous_move, mov))
+                        .collect();
+                    self.index = 0;
+
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => match self.select_best() {
+                    Some(mov) if mov == self.tt_move || self.killers.contains(&mov) => {}
+                    Some(mov) => return Some(mov),
+                    None => {
+                        self.index = 0;
+                        self.stage = Stage::BadCaptures;
+                    }
+                },
+                Stage::BadCaptures => {
+                    if let Some(&mov) = self.bad_captures.get(self.index) {
+                        self.index += 1;
+                        return Some(mov);
+                    }
+
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Selection sort step: moves the highest scored remaining move to the front and returns it
+    fn select_best(&mut self) -> Option<Move> {
+        let remaining = &self.scores[self.index..];
+        let (offset, _) = remaining
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, &score)| score)?;
+
+        let best = self.index + offset;
+        self.moves.swap(self.index, best);
+        self.scores.swap(self.index, best);
+
+        let mov = self.moves[self.index];
+        self.index += 1;
+
+        Some(mov)
+    }
+}
+
+fn capture_score(board: &Board, mov: Move) -> i32 {
+    let src_piece = board.piece_at(mov.from()).unwrap().ty();
+    // the only capture without a piece on the target square is en passant
+    let dst_piece = board
+        .piece_at(mov.to())
+        .map_or(PieceType::Pawn, |piece| piece.ty());
+
+    mmv_lva(src_piece, dst_piece)
+}
+
+fn quiet_score(
+    board: &Board,
+    ordering: &MoveOrdering,
+    ply: usize,
+    previous_move: Option<Move>,
+    mov: Move,
+) -> i32 {
+    match mov.promotion() {
+        Some(Promotion::Queen) => QUEEN_PROMOTION_SCORE,
+        Some(_) => UNDER_PROMOTION_SCORE,
+        None => ordering.quiet_score(board.side_to_move(), ply, previous_move, mov),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::board::Board;
+    use crate::moveord::picker::MovePicker;
+    use crate::moveord::MoveOrdering;
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::square::Square;
+
+    fn pick_all(board: &Board, tt_move: Move, ordering: &MoveOrdering) -> Vec<Move> {
+        let mut picker = MovePicker::new(tt_move, 0, None, ordering);
+        let mut moves = Vec::new();
+        while let Some(mov) = picker.next(board, ordering) {
+            moves.push(mov);
+        }
+        moves
+    }
+
+    #[test]
+    fn test_picks_every_legal_move_once() {
+        let board =
+            Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+
+        let tt_move = Move::new(Square::E2, Square::A6, MoveFlag::Capture);
+        let killer = Move::new(Square::A2, Square::A3, MoveFlag::Normal);
+
+        let mut ordering = MoveOrdering::default();
+        ordering.killers.store(0, killer);
+
+        let picked = pick_all(&board, tt_move, &ordering);
+        let legal = board.generate_moves();
+
+        assert_eq!(picked.len(), legal.len());
+        assert!(legal.iter().all(|mov| picked.contains(mov)));
+
+        assert_eq!(picked[0], tt_move);
+
+        // captures of defended pieces by more valuable pieces are tried after all quiet moves
+        let losing_captures = [
+            Move::new(Square::F3, Square::F6, MoveFlag::Capture),
+            Move::new(Square::E5, Square::D7, MoveFlag::Capture),
+            Move::new(Square::E5, Square::F7, MoveFlag::Capture),
+            Move::new(Square::E5, Square::G6, MoveFlag::Capture),
+            // defended by the rook on h8
+            Move::new(Square::F3, Square::H3, MoveFlag::Capture),
+        ];
+        let tail = &picked[picked.len() - losing_captures.len()..];
+        assert!(losing_captures.iter().all(|mov| tail.contains(mov)));
+
+        let first_quiet = picked.iter().position(|mov| !mov.is_capture()).unwrap();
+        assert_eq!(picked[first_quiet], killer);
+    }
+
+    #[test]
+    fn test_skips_illegal_tt_move_and_killers() {
+        let board = Board::default();
+
+        let tt_move = Move::new(Square::E4, Square::E5, MoveFlag::Normal);
+        let killer = Move::new(Square::E1, Square::E2, MoveFlag::Normal);
+
+        let mut ordering = MoveOrdering::default();
+        ordering.killers.store(0, killer);
+
+        let picked = pick_all(&board, tt_move, &ordering);
+
+        assert_eq!(picked.len(), 20);
+        assert!(!picked.contains(&tt_move));
+        assert!(!picked.contains(&killer));
+    }
+}
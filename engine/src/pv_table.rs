@@ -2,7 +2,7 @@ use std::fmt::{self, Formatter};
 
 use crate::types::chess_move::Move;
 
-const MAX_PLY: usize = 64;
+pub const MAX_PLY: usize = 64;
 const TABLE_SIZE: usize = (MAX_PLY * (MAX_PLY + 1)) / 2;
 
 type Ply = usize;
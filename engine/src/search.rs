@@ -1,21 +1,85 @@
+pub mod reductions;
+
 use crate::board::Board;
 use crate::clock::Clock;
 use crate::evaluation::hce::board_value;
+use crate::evaluation::nnue::accumulator::AccumulatorStack;
+use crate::evaluation::nnue::NNUE;
+use crate::evaluation::pawns::PawnTable;
 use crate::evaluation::Evaluation;
 use crate::movegen::MoveList;
-use crate::moveord::mmv_lva;
-use crate::pv_table::PrincipleVariationTable;
+use crate::moveord::picker::MovePicker;
+use crate::moveord::{mmv_lva, MoveOrdering};
+use crate::pv_table::{PrincipleVariationTable, MAX_PLY};
 use crate::threadpool::StopSync;
 use crate::transposition::{Entry, TranspositionTable, ValueType};
 use crate::types::chess_move::Move;
 use crate::types::color::Color;
 use crate::types::search_limits::SearchLimits;
 use crate::uci::EngineMessage;
+use arrayvec::ArrayVec;
 use instant::Instant;
+use std::cmp::Reverse;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 
+use reductions::Reductions;
+
+/// Depth from which on the root is searched with an aspiration window
+const ASPIRATION_MIN_DEPTH: u8 = 5;
+/// Initial distance of the aspiration window bounds from the previous score
+const ASPIRATION_DELTA: i16 = 25;
+
+/// Default number of principal variations searched and reported
+pub const DEFAULT_MULTI_PV: u8 = 1;
+
+/// Engine options that influence the search, shared by all search threads
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub reductions: Arc<Reductions>,
+    /// Number of best root moves that are searched with a full window and reported
+    pub multi_pv: u8,
+    /// Network loaded with the `EvalFile` option
+    pub network: Arc<NNUE>,
+    /// Evaluate positions with the network instead of the handcrafted evaluation
+    pub use_nnue: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            reductions: Arc::default(),
+            multi_pv: DEFAULT_MULTI_PV,
+            network: Arc::from(NNUE::embedded()),
+            use_nnue: false,
+        }
+    }
+}
+
+/// A legal move at the root together with the result of its latest search
+#[derive(Debug, Clone)]
+pub struct RootMove {
+    pub mov: Move,
+    /// Score of the current iteration, [`Evaluation::MIN`] if the move was not searched yet or
+    /// is not better than the best move
+    pub score: Evaluation,
+    /// Score of the previous iteration
+    pub previous_score: Evaluation,
+    pub pv: Vec<Move>,
+}
+
+impl RootMove {
+    fn new(mov: Move) -> Self {
+        Self {
+            mov,
+            score: Evaluation::MIN,
+            previous_score: Evaluation::MIN,
+            pv: vec![mov],
+        }
+    }
+}
+
 pub struct NodeCountBuffer {
     inner: Vec<AtomicU64>,
 }
@@ -45,7 +109,18 @@ pub struct Search {
     pv_table: PrincipleVariationTable,
     local_stop: bool,
     clock: Clock,
-    root_moves: MoveList,
+    /// Whether the search still runs in the opponent's time without time limits
+    pondering: bool,
+    /// Mate search mode for `go mate`. Selective pruning and reductions are turned off so that
+    /// every mate within the depth bound is found.
+    mate_search: bool,
+    root_moves: Vec<RootMove>,
+    /// Index of the principal variation that is currently searched, all moves before it are
+    /// excluded from the search at the root
+    pv_index: usize,
+    options: SearchOptions,
+    /// Only kept up to date when the network evaluates positions
+    accumulators: Option<AccumulatorStack>,
 
     engine_tx: Sender<EngineMessage>,
     tt: Arc<TranspositionTable>,
@@ -62,19 +137,31 @@ impl Search {
         limits: SearchLimits,
         clock: Clock,
         root_moves: MoveList,
+        options: SearchOptions,
         engine_tx: Sender<EngineMessage>,
         tt: Arc<TranspositionTable>,
         stop_sync: Arc<StopSync>,
         thread_id: u8,
         nodes_buffer: Arc<NodeCountBuffer>,
     ) -> Self {
+        let pondering = limits.ponder;
+        let mate_search = limits.mate.is_some();
+        let accumulators = options
+            .use_nnue
+            .then(|| AccumulatorStack::new(options.network.clone(), &board));
+
         Search {
             board,
             limits,
             pv_table: PrincipleVariationTable::new(),
             local_stop: false,
             clock,
-            root_moves,
+            pondering,
+            mate_search,
+            root_moves: root_moves.into_iter().map(RootMove::new).collect(),
+            pv_index: 0,
+            options,
+            accumulators,
 
             engine_tx,
             tt,
@@ -85,64 +172,85 @@ impl Search {
         }
     }
 
-    pub fn search(mut self, is_main: bool) -> Move {
-        self.iterative_deepening(is_main);
+    /// Runs the search on the calling thread. The move ordering tables and the pawn hash table
+    /// belong to the worker thread so that they persist between searches and are only lent to
+    /// the search.
+    pub fn search(
+        mut self,
+        is_main: bool,
+        ordering: &mut MoveOrdering,
+        pawn_table: &mut PawnTable,
+    ) -> Move {
+        self.iterative_deepening(ordering, pawn_table, is_main);
+
+        let best_move = self.root_moves[0].mov;
+        let ponder_move = if is_main { self.ponder_move() } else { None };
 
         let _guard = self.stop_sync.cond_var.wait_while(
             self.stop_sync.wait_for_stop.lock().unwrap(),
             |wait_for_stop| *wait_for_stop,
         );
 
-        let best_move = self.pv_table.best_move();
-
         if is_main {
+            let mut response = format!("bestmove {best_move}");
+            if let Some(ponder_move) = ponder_move {
+                response.push_str(&format!(" ponder {ponder_move}"));
+            }
+
+            // the GUI may change options as soon as it received the best move
+            self.stop_sync.searching.store(false, Ordering::SeqCst);
+
             self.engine_tx
-                .send(EngineMessage::Response(format!("bestmove {best_move}")))
+                .send(EngineMessage::Response(response))
                 .unwrap();
         }
 
         best_move
     }
 
-    fn iterative_deepening(&mut self, is_main: bool) {
-        let mut evaluation;
+    fn iterative_deepening(
+        &mut self,
+        ordering: &mut MoveOrdering,
+        pawn_table: &mut PawnTable,
+        is_main: bool,
+    ) {
+        let multi_pv = (self.options.multi_pv as usize).clamp(1, self.root_moves.len());
+
+        for depth in 1..MAX_PLY as u8 {
+            for root_move in &mut self.root_moves {
+                root_move.previous_score = root_move.score;
+            }
+
+            // MultiPV: search for the best move, then for the best move excluding the first one
+            // and so on
+            for pv_index in 0..multi_pv {
+                self.pv_index = pv_index;
+                self.search_root_with_aspiration(ordering, pawn_table, depth, is_main);
 
-        for depth in 1..u8::MAX {
-            evaluation =
-                self.negamax_search::<true, true>(Evaluation::MIN, Evaluation::MAX, depth, 0);
+                if self.local_stop {
+                    break;
+                }
+
+                // bring the principal variations found so far into order
+                self.root_moves[..=pv_index].sort_by_key(|root_move| Reverse(root_move.score));
+            }
 
             if self.local_stop {
                 break;
             }
 
             if is_main {
-                let line = self.pv_table.variation();
-
-                let output = format!(
-                    "info depth {} score {} time {} nodes {} pv {}",
-                    depth,
-                    if evaluation.is_mate() {
-                        format!("mate {}", evaluation.mate_full_moves())
-                    } else {
-                        format!("cp {}", evaluation)
-                    },
-                    self.clock.start.elapsed().as_millis(),
-                    self.nodes_buffer.accumulate(),
-                    line.iter()
-                        .map(|mov| mov.to_string())
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                );
-
-                self.engine_tx
-                    .send(EngineMessage::Response(output))
-                    .unwrap();
+                for pv_index in 0..multi_pv {
+                    self.report(depth, pv_index, None);
+                }
             }
 
-            if depth >= self.limits.depth.unwrap_or(u8::MAX) {
+            if depth >= self.max_depth() || self.is_mate_limit_reached() {
                 break;
             }
 
+            self.check_ponderhit();
+
             if let Some(optimum) = self.clock.optimum {
                 if optimum < Instant::now() {
                     break;
@@ -151,9 +259,162 @@ impl Search {
         }
     }
 
+    /// Depth limit of the iterative deepening. A mate in `n` moves is found within `2n - 1` plies.
+    fn max_depth(&self) -> u8 {
+        let depth = self.limits.depth.unwrap_or(u8::MAX);
+
+        match self.limits.mate {
+            Some(mate) => depth.min(mate.saturating_mul(2).saturating_sub(1).max(1)),
+            None => depth,
+        }
+    }
+
+    /// Whether `go mate` asked for a mate in at most `n` moves and the search has proven one
+    fn is_mate_limit_reached(&self) -> bool {
+        let Some(mate) = self.limits.mate else {
+            return false;
+        };
+
+        let score = self.root_moves[0].score;
+        score.is_mate()
+            && score > Evaluation::EQUALITY
+            && score.mate_full_moves() as i16 <= mate as i16
+    }
+
+    /// Switches to the time limits sent with `ponderhit` once the opponent played the expected
+    /// move. The start of the clock is kept, so the reported time covers the whole search.
+    fn check_ponderhit(&mut self) {
+        if self.pondering && !self.stop_sync.ponder.load(Ordering::Relaxed) {
+            self.pondering = false;
+
+            if let Some(clock) = *self.stop_sync.ponderhit_clock.lock().unwrap() {
+                self.clock.maximum = clock.maximum;
+                self.clock.optimum = clock.optimum;
+            }
+        }
+    }
+
+    /// The expected reply to the best move, taken from the principal variation or from the
+    /// transposition table if the variation ends after the best move
+    fn ponder_move(&mut self) -> Option<Move> {
+        let root_move = &self.root_moves[0];
+        if let Some(&ponder_move) = root_move.pv.get(1) {
+            return Some(ponder_move);
+        }
+
+        self.board.apply_move(root_move.mov);
+        let ponder_move = self
+            .tt
+            .probe(&self.board, 1)
+            .map(|entry| entry.best_move)
+            .filter(|&mov| mov != Move::NULL && self.board.is_legal(mov));
+        self.board.undo_move();
+
+        ponder_move
+    }
+
+    /// Searches the root moves starting at the current `pv_index`, with an aspiration window
+    /// around the score of the previous iteration
+    fn search_root_with_aspiration(
+        &mut self,
+        ordering: &mut MoveOrdering,
+        pawn_table: &mut PawnTable,
+        depth: u8,
+        is_main: bool,
+    ) {
+        let previous_score = self.root_moves[self.pv_index].previous_score;
+
+        // Aspiration windows: search with a narrow window around the score of the previous
+        // iteration, which is cheaper than a full window search as long as the score does
+        // not change much. The window is widened until the score lands inside of it.
+        let mut delta = ASPIRATION_DELTA;
+        let (mut alpha, mut beta) = if depth >= ASPIRATION_MIN_DEPTH && !previous_score.is_mate() {
+            (previous_score - delta, previous_score + delta)
+        } else {
+            (Evaluation::MIN, Evaluation::MAX)
+        };
+
+        // unsearched moves have to be sorted behind the searched ones in case the search is
+        // interrupted
+        for root_move in &mut self.root_moves[self.pv_index..] {
+            root_move.score = Evaluation::MIN;
+        }
+
+        loop {
+            let evaluation =
+                self.negamax_search::<true, true>(ordering, pawn_table, alpha, beta, depth, 0);
+
+            // the sort is stable, so moves with equal scores keep the order of the last iteration
+            self.root_moves[self.pv_index..].sort_by_key(|root_move| Reverse(root_move.score));
+
+            if self.local_stop {
+                break;
+            }
+
+            let bound = if evaluation <= alpha {
+                // fail low: keep the upper side of the window close to the real score
+                beta = alpha + ((beta.val() as i32 - alpha.val() as i32) / 2) as i16;
+                alpha = evaluation - delta;
+                "upperbound"
+            } else if evaluation >= beta {
+                beta = evaluation + delta;
+                "lowerbound"
+            } else {
+                break;
+            };
+
+            if is_main {
+                self.report(depth, self.pv_index, Some(bound));
+            }
+
+            delta = delta.saturating_add(delta / 2);
+        }
+    }
+
+    /// Sends the principal variation of a root move to the GUI. `bound` marks the score of a
+    /// search that failed outside of its aspiration window as `lowerbound` or `upperbound`.
+    fn report(&self, depth: u8, pv_index: usize, bound: Option<&str>) {
+        let root_move = &self.root_moves[pv_index];
+        let evaluation = root_move.score;
+
+        let mut output = format!(
+            "info depth {} multipv {} score {}",
+            depth,
+            pv_index + 1,
+            if evaluation.is_mate() {
+                format!("mate {}", evaluation.mate_full_moves())
+            } else {
+                format!("cp {}", evaluation)
+            },
+        );
+
+        if let Some(bound) = bound {
+            output.push_str(&format!(" {bound}"));
+        }
+
+        output.push_str(&format!(
+            " time {} nodes {} hashfull {} pv {}",
+            self.clock.start.elapsed().as_millis(),
+            self.nodes_buffer.accumulate(),
+            self.tt.hashfull(),
+            root_move
+                .pv
+                .iter()
+                .map(|mov| mov.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+
+        self.engine_tx
+            .send(EngineMessage::Response(output))
+            .unwrap();
+    }
+
     /// Fail soft variant of negamax search
     fn negamax_search<const PV: bool, const ROOT: bool>(
         &mut self,
+        ordering: &mut MoveOrdering,
+        pawn_table: &mut PawnTable,
         mut alpha: Evaluation,
         mut beta: Evaluation,
         depth: u8,
@@ -179,7 +440,7 @@ impl Search {
         }
 
         if depth == 0 {
-            return self.quiescence(alpha, beta, ply);
+            return self.quiescence(pawn_table, alpha, beta, ply);
         }
 
         self.nodes_buffer
@@ -193,12 +454,49 @@ impl Search {
             }
         }
 
-        let mut moves = self.board.generate_moves();
-        if moves.is_empty() {
-            if !self.board.checkers().is_empty() {
-                return Evaluation::mated_in(ply);
-            } else {
-                return Evaluation::EQUALITY;
+        let in_check = !self.board.checkers().is_empty();
+
+        // positions in check are never pruned based on the static evaluation
+        let static_eval = if in_check {
+            Evaluation::MIN
+        } else if let Some(entry) = &entry {
+            entry.static_eval
+        } else {
+            self.evaluate(pawn_table)
+        };
+
+        // Null move pruning: give the opponent a free move and search the position with a reduced
+        // depth. If the score is still above beta, the position is good enough to cut off. This
+        // is unsound in zugzwang, which is most likely to happen with only pawns and a king left.
+        if !PV
+            && !in_check
+            && !self.mate_search
+            && depth >= 3
+            && static_eval >= beta
+            && self.board.last_move().is_some()
+            && self.board.has_non_pawn_material(self.board.side_to_move())
+        {
+            let margin = static_eval.val() as i32 - beta.val() as i32;
+            let reduction = 3 + depth / 4 + (margin / 200).clamp(0, 3) as u8;
+
+            self.board.apply_null_move();
+            let score = -self.negamax_search::<false, false>(
+                ordering,
+                pawn_table,
+                -beta,
+                -beta + 1,
+                depth.saturating_sub(reduction + 1),
+                ply + 1,
+            );
+            self.board.undo_null_move();
+
+            if self.local_stop {
+                return Evaluation::INVALID;
+            }
+
+            if score >= beta {
+                // don't return unproven mate scores
+                return if score.is_mate() { beta } else { score };
             }
         }
 
@@ -206,34 +504,127 @@ impl Search {
         let mut best_score = Evaluation::MIN;
         let mut best_move = Move::NULL;
 
-        moves.sort_by_key(|mov| {
-            if let Some(entry) = &entry {
-                if &entry.best_move == mov {
-                    return -200000;
-                }
-            }
+        let tt_move = entry.as_ref().map_or(Move::NULL, |entry| entry.best_move);
+        let side_to_move = self.board.side_to_move();
+        let previous_move = self.board.last_move();
 
-            let src_piece = self.board.piece_at(mov.from()).unwrap();
-            let dst_piece = self.board.piece_at(mov.to());
-            if let Some(dst_piece) = dst_piece {
-                return -mmv_lva(src_piece.ty(), dst_piece.ty());
+        let mut picker = MovePicker::new(tt_move, ply as usize, previous_move, ordering);
+
+        let mut searched_quiets = ArrayVec::<Move, 64>::new();
+
+        let mut move_count = 0;
+
+        while let Some(chess_move) = picker.next(&self.board, ordering) {
+            if ROOT
+                && !self.root_moves[self.pv_index..]
+                    .iter()
+                    .any(|root_move| root_move.mov == chess_move)
+            {
+                continue;
             }
-            0
-        });
 
-        for chess_move in moves {
-            if ROOT && !self.root_moves.contains(&chess_move) {
+            move_count += 1;
+
+            let is_quiet = !chess_move.is_capture() && chess_move.promotion().is_none();
+            let is_killer = ordering.killers.contains(ply as usize, chess_move);
+            let is_losing = best_score.is_mate() && best_score < Evaluation::EQUALITY;
+
+            // Late move pruning: at low depth, quiet moves far down the move ordering are unlikely
+            // to raise alpha and are skipped entirely
+            if !PV
+                && !in_check
+                && !self.mate_search
+                && is_quiet
+                && !is_losing
+                && chess_move != tt_move
+                && depth <= 3
+                && move_count > late_move_pruning_threshold(depth)
+            {
                 continue;
             }
 
-            self.board.apply_move(chess_move);
-            let score = -self.negamax_search::<PV, false>(-beta, -alpha, depth - 1, ply + 1);
-            self.board.undo_move();
+            // the child probes its entry right away, start loading it while the move is applied
+            self.tt.prefetch(self.board.key_after(chess_move));
+            self.apply_move(chess_move);
+
+            let gives_check = !self.board.checkers().is_empty();
+
+            // Principal variation search: only the first move is searched with the full window.
+            // Every other move is expected to fail low and is searched with a null window around
+            // alpha. A re-search with the full window is required if the move lands inside.
+            let score = if move_count == 1 {
+                -self.negamax_search::<PV, false>(
+                    ordering,
+                    pawn_table,
+                    -beta,
+                    -alpha,
+                    depth - 1,
+                    ply + 1,
+                )
+            } else {
+                // Late move reductions: quiet moves late in the move ordering are searched with
+                // a reduced depth first and only re-searched to full depth if they beat alpha
+                let reduction = if !self.mate_search
+                    && depth >= 3
+                    && is_quiet
+                    && !is_killer
+                    && !in_check
+                    && !gives_check
+                    && chess_move != tt_move
+                {
+                    self.options
+                        .reductions
+                        .get(depth, move_count)
+                        .saturating_sub(PV as u8)
+                        .min(depth - 2)
+                } else {
+                    0
+                };
+
+                let reduced_depth = depth - 1 - reduction;
+
+                let mut score = -self.negamax_search::<false, false>(
+                    ordering,
+                    pawn_table,
+                    -alpha - 1,
+                    -alpha,
+                    reduced_depth,
+                    ply + 1,
+                );
+
+                if reduced_depth < depth - 1 && score > alpha {
+                    score = -self.negamax_search::<false, false>(
+                        ordering,
+                        pawn_table,
+                        -alpha - 1,
+                        -alpha,
+                        depth - 1,
+                        ply + 1,
+                    );
+                }
+
+                if PV && score > alpha && score < beta {
+                    score = -self.negamax_search::<true, false>(
+                        ordering,
+                        pawn_table,
+                        -beta,
+                        -alpha,
+                        depth - 1,
+                        ply + 1,
+                    );
+                }
+
+                score
+            };
+
+            self.undo_move();
 
             if self.local_stop {
                 return Evaluation::INVALID;
             }
 
+            let raises_alpha = score > alpha;
+
             if score > best_score {
                 best_score = score;
                 best_move = chess_move;
@@ -245,20 +636,75 @@ impl Search {
                 }
             }
 
+            if ROOT {
+                let root_move = self
+                    .root_moves
+                    .iter_mut()
+                    .find(|root_move| root_move.mov == chess_move)
+                    .unwrap();
+
+                // only the first move and moves raising alpha have a meaningful score, all other
+                // moves are only known to be worse
+                if move_count == 1 || raises_alpha {
+                    root_move.score = score;
+                    if raises_alpha {
+                        root_move.pv = self.pv_table.variation().to_vec();
+                    }
+                } else {
+                    root_move.score = Evaluation::MIN;
+                }
+            }
+
             if alpha >= beta {
+                if is_quiet {
+                    ordering.update_quiet_cutoff(
+                        side_to_move,
+                        ply as usize,
+                        depth,
+                        previous_move,
+                        chess_move,
+                        &searched_quiets,
+                    );
+                }
+
                 break;
             }
+
+            if is_quiet {
+                let _ = searched_quiets.try_push(chess_move);
+            }
+        }
+
+        if move_count == 0 {
+            return if in_check {
+                Evaluation::mated_in(ply)
+            } else {
+                Evaluation::EQUALITY
+            };
         }
 
         let value_type = get_value_type(best_score, original_alpha, beta);
 
-        self.tt
-            .store(&self.board, best_move, depth, best_score, value_type, ply);
+        self.tt.store(
+            &self.board,
+            best_move,
+            depth,
+            best_score,
+            value_type,
+            static_eval,
+            ply,
+        );
 
         best_score
     }
 
-    fn quiescence(&mut self, mut alpha: Evaluation, beta: Evaluation, ply: u8) -> Evaluation {
+    fn quiescence(
+        &mut self,
+        pawn_table: &mut PawnTable,
+        mut alpha: Evaluation,
+        beta: Evaluation,
+        ply: u8,
+    ) -> Evaluation {
         self.nodes_buffer
             .get(self.thread_id)
             .fetch_add(1, Ordering::Relaxed);
@@ -272,10 +718,7 @@ impl Search {
             }
         }
 
-        let evaluation = match self.board.side_to_move() {
-            Color::White => board_value(&self.board),
-            Color::Black => -board_value(&self.board),
-        };
+        let evaluation = self.evaluate(pawn_table);
 
         alpha = alpha.max(evaluation);
 
@@ -296,9 +739,14 @@ impl Search {
 
         let mut best_score = evaluation;
         for chess_move in moves {
-            self.board.apply_move(chess_move);
-            let score = -self.quiescence(-beta, -alpha, ply + 1);
-            self.board.undo_move();
+            // captures that lose material are very unlikely to raise alpha
+            if !self.board.see(chess_move, 0) {
+                continue;
+            }
+
+            self.apply_move(chess_move);
+            let score = -self.quiescence(pawn_table, -beta, -alpha, ply + 1);
+            self.undo_move();
 
             if score > best_score {
                 best_score = score;
@@ -314,6 +762,35 @@ impl Search {
         best_score
     }
 
+    /// Applies a move to the board and updates the network accumulators
+    fn apply_move(&mut self, mov: Move) {
+        if let Some(accumulators) = &mut self.accumulators {
+            accumulators.push(&self.board, mov);
+        }
+        self.board.apply_move(mov);
+    }
+
+    fn undo_move(&mut self) {
+        if let Some(accumulators) = &mut self.accumulators {
+            accumulators.pop();
+        }
+        self.board.undo_move();
+    }
+
+    /// Static evaluation from the perspective of the side to move
+    fn evaluate(&mut self, pawn_table: &mut PawnTable) -> Evaluation {
+        let side_to_move = self.board.side_to_move();
+
+        if let Some(accumulators) = &self.accumulators {
+            return Evaluation::from_centipawns(accumulators.evaluate(side_to_move));
+        }
+
+        match side_to_move {
+            Color::White => board_value(&self.board, pawn_table),
+            Color::Black => -board_value(&self.board, pawn_table),
+        }
+    }
+
     fn should_interrupt(&mut self) -> bool {
         self.call_cnt -= 1;
         if self.call_cnt > 0 {
@@ -322,6 +799,8 @@ impl Search {
 
         self.call_cnt = 4096;
 
+        self.check_ponderhit();
+
         let nodes = self.nodes_buffer.accumulate();
 
         if let Some(max_nodes) = self.limits.nodes {
@@ -357,6 +836,11 @@ fn get_value_type(score: Evaluation, alpha: Evaluation, beta: Evaluation) -> Val
     }
 }
 
+/// Number of moves searched before late move pruning kicks in
+fn late_move_pruning_threshold(depth: u8) -> usize {
+    3 + (depth as usize) * (depth as usize)
+}
+
 fn tt_cutoff(entry: &Entry, alpha: Evaluation, beta: Evaluation) -> bool {
     match entry.value_type {
         ValueType::Exact => true,
@@ -364,3 +848,87 @@ fn tt_cutoff(entry: &Entry, alpha: Evaluation, beta: Evaluation) -> bool {
         ValueType::Upperbound => entry.value <= alpha,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::mpsc::channel;
+    use std::sync::Arc;
+
+    use crate::board::Board;
+    use crate::clock::Clock;
+    use crate::evaluation::pawns::PawnTable;
+    use crate::moveord::MoveOrdering;
+    use crate::search::{NodeCountBuffer, Search, SearchOptions};
+    use crate::threadpool::StopSync;
+    use crate::transposition::TranspositionTable;
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::search_limits::{SearchLimits, TimeLimit};
+    use crate::types::square::Square;
+    use crate::uci::EngineMessage;
+
+    fn search_mate(fen: &str, mate: u8) -> (Move, Vec<String>) {
+        let board = Board::from_str(fen).unwrap();
+        let limits = SearchLimits {
+            mate: Some(mate),
+            time: TimeLimit::External,
+            ..Default::default()
+        };
+        let clock = Clock::new(&limits.time, board.game_ply(), board.side_to_move());
+        let root_moves = board.generate_moves();
+        let (engine_tx, engine_rx) = channel();
+
+        let search = Search::new(
+            board,
+            limits,
+            clock,
+            root_moves,
+            SearchOptions::default(),
+            engine_tx,
+            Arc::new(TranspositionTable::new(1)),
+            Arc::new(StopSync::default()),
+            0,
+            Arc::new(NodeCountBuffer::new(1)),
+        );
+
+        let best_move = search.search(
+            true,
+            &mut Box::<MoveOrdering>::default(),
+            &mut PawnTable::new(),
+        );
+
+        let output = engine_rx
+            .try_iter()
+            .filter_map(|message| match message {
+                EngineMessage::Response(response) => Some(response),
+                _ => None,
+            })
+            .collect();
+
+        (best_move, output)
+    }
+
+    #[test]
+    fn test_go_mate() {
+        let (best_move, output) = search_mate(
+            "r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1",
+            3,
+        );
+
+        assert_eq!(
+            best_move,
+            Move::new(Square::F8, Square::C5, MoveFlag::Normal)
+        );
+        assert!(output.iter().any(|line| line.contains("score mate 3 ")));
+        assert!(output.last().unwrap().starts_with("bestmove f8c5"));
+    }
+
+    #[test]
+    fn test_go_mate_stops_at_depth_bound() {
+        let (_, output) = search_mate(Board::STARTING_POS_FEN, 2);
+
+        // a mate in two moves is found within three plies
+        assert!(output.iter().any(|line| line.starts_with("info depth 3 ")));
+        assert!(!output.iter().any(|line| line.starts_with("info depth 4 ")));
+    }
+}
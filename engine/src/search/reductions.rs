@@ -0,0 +1,102 @@
+use crate::pv_table::MAX_PLY;
+
+const MAX_MOVES: usize = 64;
+
+/// Default constant part of the reduction in hundredths of a ply
+pub const DEFAULT_LMR_BASE: u16 = 75;
+
+/// Default divisor of the logarithmic part of the reduction in hundredths
+pub const DEFAULT_LMR_DIVISOR: u16 = 225;
+
+/// Late move reduction table indexed by remaining depth and move number.
+///
+/// The reduction grows logarithmically with both the depth and the position of the move in the
+/// move ordering: `base + ln(depth) * ln(move_number) / divisor`.
+#[derive(Debug, Clone)]
+pub struct Reductions {
+    base: u16,
+    divisor: u16,
+    table: [[u8; MAX_MOVES]; MAX_PLY],
+}
+
+impl Reductions {
+    /// Builds the table with `base` and `divisor` given in hundredths
+    pub fn new(base: u16, divisor: u16) -> Self {
+        let mut table = [[0; MAX_MOVES]; MAX_PLY];
+
+        let base_f = base as f64 / 100.0;
+        let divisor_f = (divisor as f64 / 100.0).max(0.01);
+
+        for (depth, row) in table.iter_mut().enumerate().skip(1) {
+            for (move_number, reduction) in row.iter_mut().enumerate().skip(1) {
+                let value = base_f + (depth as f64).ln() * (move_number as f64).ln() / divisor_f;
+                *reduction = value.clamp(0.0, u8::MAX as f64) as u8;
+            }
+        }
+
+        Self {
+            base,
+            divisor,
+            table,
+        }
+    }
+
+    /// Returns the reduction for a move at the given remaining depth and (1-based) move number
+    pub fn get(&self, depth: u8, move_number: usize) -> u8 {
+        self.table[(depth as usize).min(MAX_PLY - 1)][move_number.min(MAX_MOVES - 1)]
+    }
+
+    pub fn base(&self) -> u16 {
+        self.base
+    }
+
+    pub fn divisor(&self) -> u16 {
+        self.divisor
+    }
+}
+
+impl Default for Reductions {
+    fn default() -> Self {
+        Self::new(DEFAULT_LMR_BASE, DEFAULT_LMR_DIVISOR)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pv_table::MAX_PLY;
+    use crate::search::reductions::{Reductions, DEFAULT_LMR_DIVISOR, MAX_MOVES};
+
+    #[test]
+    fn test_monotonic() {
+        let reductions = Reductions::default();
+
+        for depth in 1..MAX_PLY as u8 {
+            for move_number in 2..MAX_MOVES {
+                assert!(
+                    reductions.get(depth, move_number) >= reductions.get(depth, move_number - 1)
+                );
+                assert!(
+                    reductions.get(depth, move_number) >= reductions.get(depth - 1, move_number)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_first_move_is_not_reduced_by_logarithmic_term() {
+        let reductions = Reductions::new(0, DEFAULT_LMR_DIVISOR);
+
+        for depth in 1..MAX_PLY as u8 {
+            assert_eq!(reductions.get(depth, 1), 0);
+        }
+    }
+
+    #[test]
+    fn test_clamped_lookup() {
+        let reductions = Reductions::default();
+        assert_eq!(
+            reductions.get(u8::MAX, 1000),
+            reductions.get(MAX_PLY as u8 - 1, MAX_MOVES - 1)
+        );
+    }
+}
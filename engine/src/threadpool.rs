@@ -10,7 +10,9 @@ use std::{
 use crate::{
     board::Board,
     clock::Clock,
-    search::{NodeCountBuffer, Search},
+    evaluation::pawns::PawnTable,
+    moveord::MoveOrdering,
+    search::{NodeCountBuffer, Search, SearchOptions},
     transposition::TranspositionTable,
     types::search_limits::{SearchLimits, TimeLimit},
     uci::EngineMessage,
@@ -22,6 +24,12 @@ pub struct StopSync {
     pub stop: AtomicBool,
     pub wait_for_stop: Mutex<bool>,
     pub cond_var: Condvar,
+    /// Set while a ponder search runs without time limits
+    pub ponder: AtomicBool,
+    /// Time limits of the ponder search after `ponderhit`
+    pub ponderhit_clock: Mutex<Option<Clock>>,
+    /// Set from the start of a search until its best move is sent
+    pub searching: AtomicBool,
 }
 
 pub struct ThreadPool<S: ThreadSpawner> {
@@ -58,6 +66,7 @@ impl<S: ThreadSpawner> ThreadPool<S> {
         board: Board,
         limits: SearchLimits,
         clock: Clock,
+        options: SearchOptions,
         engine_tx: Sender<EngineMessage>,
         tt: Arc<TranspositionTable>,
     ) {
@@ -97,6 +106,13 @@ impl<S: ThreadSpawner> ThreadPool<S> {
 
         let nodes_buffer = Arc::new(NodeCountBuffer::new(self.workers.len() as u8));
 
+        tt.new_search();
+
+        // set before the workers start, so that an early ponderhit is not lost
+        *self.stop_sync.ponderhit_clock.lock().unwrap() = None;
+        self.stop_sync.ponder.store(limits.ponder, Ordering::SeqCst);
+        self.stop_sync.searching.store(true, Ordering::SeqCst);
+
         // assign workers search job
         for worker in &self.workers {
             worker
@@ -106,6 +122,7 @@ impl<S: ThreadSpawner> ThreadPool<S> {
                     limits.clone(),
                     clock,
                     root_moves.clone(),
+                    options.clone(),
                     engine_tx.clone(),
                     tt.clone(),
                     self.stop_sync.clone(),
@@ -116,6 +133,10 @@ impl<S: ThreadSpawner> ThreadPool<S> {
         }
     }
 
+    pub fn is_searching(&self) -> bool {
+        self.stop_sync.searching.load(Ordering::SeqCst)
+    }
+
     pub fn stop_search(&self) {
         let mut wait_for_stop = self.stop_sync.wait_for_stop.lock().unwrap();
         *wait_for_stop = false;
@@ -125,6 +146,18 @@ impl<S: ThreadSpawner> ThreadPool<S> {
         self.stop_sync.stop.store(true, Ordering::SeqCst);
     }
 
+    /// Turns the running ponder search into a regular search limited by `time`, an infinite
+    /// search keeps waiting for `stop`
+    pub fn ponderhit(&self, time: &TimeLimit, clock: Clock) {
+        *self.stop_sync.ponderhit_clock.lock().unwrap() = Some(clock);
+        self.stop_sync.ponder.store(false, Ordering::SeqCst);
+
+        let mut wait_for_stop = self.stop_sync.wait_for_stop.lock().unwrap();
+        *wait_for_stop = *time == TimeLimit::Infinite;
+        drop(wait_for_stop);
+        self.stop_sync.cond_var.notify_all();
+    }
+
     pub fn resize(&mut self, num_threads: u8) {
         let new_barrier = Arc::new(Barrier::new(num_threads as usize));
 
@@ -153,6 +186,8 @@ impl<S: ThreadSpawner> ThreadPool<S> {
         });
     }
 
+    /// Clears the transposition table, the move ordering tables and the pawn hash tables, with
+    /// every thread zeroing a part of the transposition table
     pub fn clear(&self, tt: Arc<TranspositionTable>) {
         for worker in &self.workers {
             worker.worker_tx.send(Job::Clear(tt.clone())).unwrap();
@@ -206,6 +241,8 @@ impl Worker {
         S::spawn(move || {
             let mut barrier = barrier;
             let mut num_threads = num_threads;
+            let mut ordering = Box::<MoveOrdering>::default();
+            let mut pawn_table = PawnTable::new();
 
             loop {
                 eprintln!("[{thread_id}] waiting for job!");
@@ -218,12 +255,13 @@ impl Worker {
 
                                 let mut wait_for_stop = stop_sync.wait_for_stop.lock().unwrap();
                                 // set to false if not infinite search or ponder
-                                *wait_for_stop = search.limits().time == TimeLimit::Infinite;
+                                *wait_for_stop = search.limits().time == TimeLimit::Infinite
+                                    || stop_sync.ponder.load(Ordering::SeqCst);
                                 drop(wait_for_stop);
                             }
 
                             let wait = barrier.wait();
-                            search.search(wait.is_leader());
+                            search.search(wait.is_leader(), &mut ordering, &mut pawn_table);
 
                             barrier.wait();
                         }
@@ -237,12 +275,11 @@ impl Worker {
                         Job::Clear(transposition_table) => {
                             barrier.wait();
 
-                            let chunk =
-                                transposition_table.chunk(thread_id as usize, num_threads as usize);
+                            transposition_table
+                                .clear_chunk(thread_id as usize, num_threads as usize);
 
-                            for val in chunk {
-                                val.store(0, Ordering::Relaxed);
-                            }
+                            ordering.clear();
+                            pawn_table.clear();
 
                             barrier.wait();
                         }
@@ -274,3 +311,67 @@ impl Worker {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc::{channel, RecvTimeoutError};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::board::Board;
+    use crate::clock::Clock;
+    use crate::search::SearchOptions;
+    use crate::threadpool::ThreadPool;
+    use crate::transposition::TranspositionTable;
+    use crate::types::search_limits::{SearchLimits, TimeLimit};
+    use crate::uci::EngineMessage;
+    use crate::ThreadSpawner;
+
+    struct TestSpawner;
+    impl ThreadSpawner for TestSpawner {
+        fn spawn<F>(f: F)
+        where
+            F: FnOnce() + Send + 'static,
+        {
+            thread::spawn(f);
+        }
+    }
+
+    #[test]
+    fn test_ponderhit_on_infinite_search_waits_for_stop() {
+        let threadpool = ThreadPool::<TestSpawner>::new(1);
+        let board = Board::default();
+        let limits = SearchLimits {
+            depth: Some(1),
+            ponder: true,
+            ..Default::default()
+        };
+        let clock = Clock::new(&limits.time, board.game_ply(), board.side_to_move());
+        let (engine_tx, engine_rx) = channel();
+
+        threadpool.search(
+            board,
+            limits,
+            clock,
+            SearchOptions::default(),
+            engine_tx,
+            Arc::new(TranspositionTable::new(1)),
+        );
+        threadpool.ponderhit(&TimeLimit::Infinite, clock);
+
+        let best_move = || loop {
+            match engine_rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(EngineMessage::Response(response)) if response.starts_with("bestmove") => {
+                    return Ok(response);
+                }
+                Ok(_) => continue,
+                Err(err) => return Err(err),
+            }
+        };
+
+        assert_eq!(best_move(), Err(RecvTimeoutError::Timeout));
+        threadpool.stop_search();
+        assert!(best_move().is_ok());
+    }
+}
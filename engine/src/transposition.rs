@@ -1,47 +1,160 @@
+mod memory;
+
+use std::alloc::{self, Layout};
 use std::fmt::Debug;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 
 use crate::board::Board;
 use crate::evaluation::Evaluation;
 use crate::types::chess_move::Move;
 
-#[derive()]
+use memory::BucketMemory;
+pub use memory::PageKind;
+
+/// Number of entries sharing one cache line
+const ENTRIES_PER_BUCKET: usize = 4;
+
+/// The generation is stored in the upper 6 bits of the generation/bound byte
+const GENERATION_BITS: u32 = 6;
+const GENERATION_MASK: u8 = (1 << GENERATION_BITS) - 1;
+
+/// Plies of depth that one generation of age is worth when choosing the entry to replace
+const AGE_WEIGHT: i32 = 8;
+
+/// Number of entries looked at by [`TranspositionTable::hashfull`]
+const HASHFULL_SAMPLE_SIZE: usize = 1000;
+
+/// An entry stored as two words without locking. The key word holds the hash xor the data word,
+/// so an entry torn by concurrent writes of two threads fails verification and is ignored instead
+/// of handing out a move of a different position (Hyatt's lockless hashing).
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    /// Returns the data word if the slot holds an entry of the position with the given hash
+    fn read(&self, hash: u64) -> Option<u64> {
+        let key = self.key.load(Ordering::Relaxed);
+        let data = self.data.load(Ordering::Relaxed);
+
+        (data != 0 && key ^ data == hash).then_some(data)
+    }
+
+    fn write(&self, hash: u64, data: u64) {
+        self.key.store(hash ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.key.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Identifies files written by [`TranspositionTable::save`]
+const FILE_MAGIC: [u8; 8] = *b"SAIPHTT\0";
+/// Has to be incremented whenever the entry layout changes
+const FILE_VERSION: u32 = 1;
+/// Size of one entry in bytes
+const ENTRY_SIZE: u32 = std::mem::size_of::<Slot>() as u32;
+/// Size of the file header: magic, version, entry size, entries per bucket, generation and
+/// number of buckets
+const FILE_HEADER_SIZE: u64 = 8 + 4 * 4 + 8;
+
+#[repr(align(64))]
+struct Bucket {
+    entries: [Slot; ENTRIES_PER_BUCKET],
+}
+
+const _: () = assert!(std::mem::size_of::<Bucket>() == 64);
+
 pub struct TranspositionTable {
-    inner: Vec<AtomicU64>,
+    inner: BucketMemory,
+    /// Incremented for every new search to tell entries of previous searches apart
+    generation: AtomicU8,
 }
 
 impl TranspositionTable {
     pub fn new(size_mb: usize) -> Self {
         let table_size = 0x100000 * size_mb;
-        let num_entries = table_size / std::mem::size_of::<AtomicU64>();
+        let num_buckets = (table_size / std::mem::size_of::<Bucket>()).max(1);
 
-        let inner = vec![0u64; num_entries]
-            .into_iter()
-            .map(AtomicU64::new)
-            .collect();
+        let inner = BucketMemory::zeroed(num_buckets).unwrap_or_else(|| {
+            alloc::handle_alloc_error(Layout::array::<Bucket>(num_buckets).unwrap())
+        });
 
-        Self { inner }
+        Self {
+            inner,
+            generation: AtomicU8::new(0),
+        }
     }
 
+    /// Starts a new generation. Entries of older generations are preferred for replacement.
+    pub fn new_search(&self) {
+        let generation = self.generation();
+        self.generation
+            .store((generation + 1) & GENERATION_MASK, Ordering::Relaxed);
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn store(
         &self,
         board: &Board,
-        best_move: Move,
+        mut best_move: Move,
         depth: u8,
         mut value: Evaluation,
         value_type: ValueType,
+        static_eval: Evaluation,
         ply: u8,
     ) {
-        let index = board.hash() % self.inner.len() as u64;
+        let hash = board.hash();
+        let generation = self.generation();
+        let bucket = self.bucket(hash);
+
+        // replacement scheme: an entry of the same position is always overwritten unless it is
+        // from the same search and was searched much deeper. Otherwise the least valuable entry
+        // is replaced, where entries of older searches lose value with every generation.
+        let mut replace = &bucket.entries[0];
+        let mut replace_score = i32::MAX;
+
+        for slot in &bucket.entries {
+            if let Some(data) = slot.read(hash) {
+                let (old_entry, old_generation) = Entry::unpack(data);
+
+                if value_type != ValueType::Exact
+                    && old_generation == generation
+                    && depth + 4 < old_entry.depth
+                {
+                    return;
+                }
+
+                // keep the best move of a previous search of the position
+                if best_move == Move::NULL {
+                    best_move = old_entry.best_move;
+                }
+
+                replace = slot;
+                break;
+            }
 
-        // replacement scheme
-        let old_entry = self.inner[index as usize].load(Ordering::Relaxed);
+            let data = slot.data.load(Ordering::Relaxed);
+            let (old_entry, old_generation) = Entry::unpack(data);
 
-        // SAFETY: we statically asserted that an entry is exactly 8 bytes
-        let old_entry: Entry = unsafe { std::mem::transmute(old_entry) };
-        if depth < old_entry.depth {
-            // don't replace when the new entry is less deeply analyzed as the old entry
-            return;
+            let age = generation.wrapping_sub(old_generation) & GENERATION_MASK;
+            let score = if data == 0 {
+                i32::MIN
+            } else {
+                old_entry.depth as i32 - AGE_WEIGHT * age as i32
+            };
+
+            if score < replace_score {
+                replace = slot;
+                replace_score = score;
+            }
         }
 
         if value.is_mate() {
@@ -49,29 +162,22 @@ impl TranspositionTable {
         }
 
         let entry = Entry {
-            hash_key: board.hash() as u16,
             best_move,
             depth,
             value,
             value_type,
+            static_eval,
         };
 
-        let entry: u64 = unsafe { std::mem::transmute(entry) };
-
-        self.inner[index as usize].store(entry, Ordering::Relaxed);
+        replace.write(hash, entry.pack(generation));
     }
 
     pub fn probe(&self, board: &Board, ply: u8) -> Option<Entry> {
-        let index = board.hash() % self.inner.len() as u64;
-
-        let entry = self.inner[index as usize].load(Ordering::Relaxed);
+        let hash = board.hash();
+        let bucket = self.bucket(hash);
 
-        // SAFETY: we statically asserted that an entry is exactly 8 bytes
-        let mut entry: Entry = unsafe { std::mem::transmute(entry) };
-
-        if entry.hash_key != board.hash() as u16 {
-            return None;
-        }
+        let data = bucket.entries.iter().find_map(|slot| slot.read(hash))?;
+        let (mut entry, _) = Entry::unpack(data);
 
         if entry.value.is_mate() {
             entry.value = entry.value.tt_to_score(ply)
@@ -80,28 +186,229 @@ impl TranspositionTable {
         Some(entry)
     }
 
-    pub fn chunk(&self, chunk_index: usize, num_chunks: usize) -> &[AtomicU64] {
+    /// Estimates how full the table is in permill by sampling the entries at its beginning.
+    /// Only entries of the current search are counted.
+    pub fn hashfull(&self) -> usize {
+        let generation = self.generation();
+
+        let sample = self
+            .inner
+            .iter()
+            .flat_map(|bucket| bucket.entries.iter())
+            .take(HASHFULL_SAMPLE_SIZE);
+
+        let mut sampled = 0;
+        let mut used = 0;
+
+        for slot in sample {
+            let data = slot.data.load(Ordering::Relaxed);
+            sampled += 1;
+            if data != 0 && Entry::unpack(data).1 == generation {
+                used += 1;
+            }
+        }
+
+        used * 1000 / sampled.max(1)
+    }
+
+    /// Zeroes a part of the table, such that all `num_chunks` parts together cover the whole
+    /// table. This allows clearing the table with multiple threads.
+    pub fn clear_chunk(&self, chunk_index: usize, num_chunks: usize) {
         let stride = self.inner.len() / num_chunks;
         let start = stride * chunk_index;
-        let end = (start + stride).min(self.inner.len());
+        let end = if chunk_index + 1 == num_chunks {
+            self.inner.len()
+        } else {
+            start + stride
+        };
+
+        for bucket in &self.inner[start..end] {
+            for slot in &bucket.entries {
+                slot.clear();
+            }
+        }
+
+        if chunk_index == 0 {
+            self.generation.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Writes the table to a file, such that a later analysis of the same positions can continue
+    /// with [`TranspositionTable::load`]. The file starts with a header recording the format
+    /// version, the entry layout and the number of buckets followed by the raw entries.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_all(&FILE_MAGIC)?;
+        writer.write_all(&FILE_VERSION.to_le_bytes())?;
+        writer.write_all(&ENTRY_SIZE.to_le_bytes())?;
+        writer.write_all(&(ENTRIES_PER_BUCKET as u32).to_le_bytes())?;
+        writer.write_all(&(self.generation() as u32).to_le_bytes())?;
+        writer.write_all(&(self.inner.len() as u64).to_le_bytes())?;
+
+        for slot in self.inner.iter().flat_map(|bucket| bucket.entries.iter()) {
+            writer.write_all(&slot.key.load(Ordering::Relaxed).to_le_bytes())?;
+            writer.write_all(&slot.data.load(Ordering::Relaxed).to_le_bytes())?;
+        }
+
+        writer.flush()
+    }
+
+    /// Reads a table written by [`TranspositionTable::save`]. The table gets the size of the saved
+    /// one. Files of other versions or entry layouts are rejected.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != FILE_MAGIC {
+            return Err(invalid_data("not a transposition table file"));
+        }
+
+        let version = read_u32(&mut reader)?;
+        let entry_size = read_u32(&mut reader)?;
+        let entries_per_bucket = read_u32(&mut reader)?;
+        if version != FILE_VERSION
+            || entry_size != ENTRY_SIZE
+            || entries_per_bucket != ENTRIES_PER_BUCKET as u32
+        {
+            return Err(invalid_data("unsupported transposition table format"));
+        }
+
+        let generation = read_u32(&mut reader)?;
+        if generation > GENERATION_MASK as u32 {
+            return Err(invalid_data("invalid generation"));
+        }
+
+        let mut num_buckets = [0; 8];
+        reader.read_exact(&mut num_buckets)?;
+        let num_buckets = u64::from_le_bytes(num_buckets);
+
+        // checked before allocating, so a corrupt header can't request an arbitrarily large table
+        let expected_size = num_buckets
+            .checked_mul(ENTRIES_PER_BUCKET as u64 * ENTRY_SIZE as u64)
+            .and_then(|size| size.checked_add(FILE_HEADER_SIZE));
+        if expected_size != Some(file_size) {
+            return Err(invalid_data("file size does not match the header"));
+        }
+
+        let num_buckets =
+            usize::try_from(num_buckets).map_err(|_| invalid_data("table too large"))?;
+
+        if num_buckets == 0 {
+            return Err(invalid_data("empty table"));
+        }
+
+        let inner = BucketMemory::zeroed(num_buckets)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::OutOfMemory, "table too large"))?;
+
+        for slot in inner.iter().flat_map(|bucket| bucket.entries.iter()) {
+            slot.key.store(read_u64(&mut reader)?, Ordering::Relaxed);
+            slot.data.store(read_u64(&mut reader)?, Ordering::Relaxed);
+        }
+
+        Ok(Self {
+            inner,
+            generation: AtomicU8::new(generation as u8),
+        })
+    }
+
+    /// Kind of pages the table was allocated with
+    pub fn page_kind(&self) -> PageKind {
+        self.inner.page_kind()
+    }
+
+    /// Size of the table in MB
+    pub fn size_mb(&self) -> usize {
+        self.inner.len() * std::mem::size_of::<Bucket>() / 0x100000
+    }
+
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Hints the CPU to load the bucket of the position with the given hash into the cache, so a
+    /// later probe or store doesn't wait for memory
+    #[inline]
+    pub fn prefetch(&self, hash: u64) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+            let bucket = self.bucket(hash) as *const Bucket as *const i8;
+            // SAFETY: prefetching has no observable effect and the pointer is valid anyway
+            unsafe { _mm_prefetch::<_MM_HINT_T0>(bucket) };
+        }
+
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = hash;
+    }
 
-        &self.inner[start..end]
+    /// Maps the hash onto the buckets with a multiplication instead of a modulo
+    fn bucket(&self, hash: u64) -> &Bucket {
+        let index = (hash as u128 * self.inner.len() as u128) >> 64;
+        &self.inner[index as usize]
     }
 }
 
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
 #[derive(Debug, Clone)]
 pub struct Entry {
-    pub hash_key: u16,
     pub best_move: Move,
     pub depth: u8,
     pub value: Evaluation,
     pub value_type: ValueType,
+    /// Static evaluation of the position, saves evaluating it again when it is revisited
+    pub static_eval: Evaluation,
 }
 
-// entry has to fit into a u64
-const _: () = assert!(std::mem::size_of::<Entry>() == 8);
+impl Entry {
+    /// Packs the entry into 64 bits:
+    /// move (16) | value (16) | static eval (16) | depth (8) | generation (6) and value type (2)
+    fn pack(&self, generation: u8) -> u64 {
+        let generation_and_type = (generation << 2) | self.value_type as u8;
+
+        self.best_move.to_bits() as u64
+            | (self.value.val() as u16 as u64) << 16
+            | (self.static_eval.val() as u16 as u64) << 32
+            | (self.depth as u64) << 48
+            | (generation_and_type as u64) << 56
+    }
+
+    /// Returns the entry and its generation
+    fn unpack(data: u64) -> (Self, u8) {
+        let generation_and_type = (data >> 56) as u8;
+
+        let entry = Self {
+            best_move: Move::from_bits(data as u16),
+            value: Evaluation::from_val((data >> 16) as u16 as i16),
+            static_eval: Evaluation::from_val((data >> 32) as u16 as i16),
+            depth: (data >> 48) as u8,
+            value_type: ValueType::from_bits(generation_and_type & 0b11),
+        };
 
-#[derive(Debug, Clone, PartialEq)]
+        (entry, generation_and_type >> 2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum ValueType {
     Exact,
@@ -110,3 +417,231 @@ pub enum ValueType {
     /// Beta
     Lowerbound,
 }
+
+impl ValueType {
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            1 => ValueType::Upperbound,
+            2 => ValueType::Lowerbound,
+            _ => ValueType::Exact,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use crate::board::Board;
+    use crate::evaluation::Evaluation;
+    use crate::transposition::{
+        Entry, TranspositionTable, ValueType, ENTRIES_PER_BUCKET, FILE_HEADER_SIZE,
+    };
+    use crate::types::chess_move::{Move, MoveFlag};
+    use crate::types::square::Square;
+
+    #[test]
+    fn test_pack_entry() {
+        let entry = Entry {
+            best_move: Move::new(Square::E7, Square::E8, MoveFlag::QueenPromotion),
+            depth: 17,
+            value: Evaluation::from_val(-1234),
+            value_type: ValueType::Lowerbound,
+            static_eval: Evaluation::from_val(-87),
+        };
+
+        let (unpacked, generation) = Entry::unpack(entry.pack(42));
+        assert_eq!(generation, 42);
+        assert_eq!(unpacked.best_move, entry.best_move);
+        assert_eq!(unpacked.depth, entry.depth);
+        assert_eq!(unpacked.value, entry.value);
+        assert_eq!(unpacked.value_type, entry.value_type);
+        assert_eq!(unpacked.static_eval, entry.static_eval);
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let board = Board::default();
+        let best_move = Move::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+
+        assert!(tt.probe(&board, 0).is_none());
+
+        tt.store(
+            &board,
+            best_move,
+            5,
+            Evaluation::from_val(30),
+            ValueType::Exact,
+            Evaluation::from_val(12),
+            0,
+        );
+
+        // prefetching is only a hint and doesn't change the table
+        tt.prefetch(board.hash());
+
+        let entry = tt.probe(&board, 0).unwrap();
+        assert_eq!(entry.best_move, best_move);
+        assert_eq!(entry.depth, 5);
+        assert_eq!(entry.value, Evaluation::from_val(30));
+        assert_eq!(entry.static_eval, Evaluation::from_val(12));
+
+        // a shallower search without a best move keeps the old move
+        tt.store(
+            &board,
+            Move::NULL,
+            3,
+            Evaluation::from_val(10),
+            ValueType::Upperbound,
+            Evaluation::from_val(12),
+            0,
+        );
+        let entry = tt.probe(&board, 0).unwrap();
+        assert_eq!(entry.best_move, best_move);
+        assert_eq!(entry.depth, 3);
+    }
+
+    #[test]
+    fn test_torn_entry_is_rejected() {
+        let tt = TranspositionTable::new(1);
+        let board = Board::default();
+
+        tt.store(
+            &board,
+            Move::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush),
+            5,
+            Evaluation::from_val(30),
+            ValueType::Exact,
+            Evaluation::from_val(12),
+            0,
+        );
+
+        // simulate another thread overwriting only the data word of the entry
+        let slot = tt
+            .bucket(board.hash())
+            .entries
+            .iter()
+            .find(|slot| slot.read(board.hash()).is_some())
+            .unwrap();
+        let other = Entry {
+            best_move: Move::new(Square::A7, Square::A8, MoveFlag::QueenPromotion),
+            depth: 9,
+            value: Evaluation::EQUALITY,
+            value_type: ValueType::Lowerbound,
+            static_eval: Evaluation::EQUALITY,
+        };
+        slot.data
+            .store(other.pack(0), std::sync::atomic::Ordering::Relaxed);
+
+        assert!(tt.probe(&board, 0).is_none());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("saiph-tt-{}.bin", std::process::id()));
+
+        let tt = TranspositionTable::new(1);
+        let board = Board::default();
+        let best_move = Move::new(Square::G1, Square::F3, MoveFlag::Normal);
+
+        tt.new_search();
+        tt.store(
+            &board,
+            best_move,
+            7,
+            Evaluation::from_val(25),
+            ValueType::Lowerbound,
+            Evaluation::from_val(15),
+            0,
+        );
+        tt.save(&path).unwrap();
+
+        let loaded = TranspositionTable::load(&path).unwrap();
+        assert_eq!(loaded.inner.len(), tt.inner.len());
+        assert_eq!(loaded.generation(), tt.generation());
+        assert_eq!(loaded.hashfull(), tt.hashfull());
+
+        let entry = loaded.probe(&board, 0).unwrap();
+        assert_eq!(entry.best_move, best_move);
+        assert_eq!(entry.depth, 7);
+        assert_eq!(entry.value_type, ValueType::Lowerbound);
+        assert_eq!(entry.static_eval, Evaluation::from_val(15));
+
+        // a file with another entry layout is rejected
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[12] += 1;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(TranspositionTable::load(&path).is_err());
+
+        // as is a truncated file
+        bytes[12] -= 1;
+        bytes.truncate(bytes.len() - 1);
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(TranspositionTable::load(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_checks_size_before_allocating() {
+        let path = std::env::temp_dir().join(format!("saiph-tt-size-{}.bin", std::process::id()));
+
+        TranspositionTable::new(1).save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let load = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            TranspositionTable::load(&path)
+                .map(|_| ())
+                .unwrap_err()
+                .kind()
+        };
+
+        // a truncated file is rejected by the header check and not once reading runs out of data
+        assert_eq!(load(&bytes[..bytes.len() / 2]), io::ErrorKind::InvalidData);
+
+        let mut too_large = bytes[..FILE_HEADER_SIZE as usize].to_vec();
+        too_large[24..32].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        assert_eq!(load(&too_large), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_allocation_is_aligned_and_empty() {
+        let tt = TranspositionTable::new(2);
+        assert_eq!(tt.size_mb(), 2);
+        assert_eq!(tt.inner.as_ptr() as usize % 64, 0);
+        assert_eq!(tt.hashfull(), 0);
+        assert!(tt.probe(&Board::default(), 0).is_none());
+    }
+
+    #[test]
+    fn test_hashfull_and_aging() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+
+        // fill the first bucket with entries of the first search
+        let bucket = tt.bucket(0);
+        for (i, slot) in bucket.entries.iter().enumerate() {
+            let entry = Entry {
+                best_move: Move::NULL,
+                depth: 10,
+                value: Evaluation::EQUALITY,
+                value_type: ValueType::Exact,
+                static_eval: Evaluation::EQUALITY,
+            };
+            slot.write(i as u64 + 1, entry.pack(0));
+        }
+        assert_eq!(tt.hashfull(), ENTRIES_PER_BUCKET);
+
+        // entries of old searches don't count
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+
+        tt.clear_chunk(0, 1);
+        assert!(bucket
+            .entries
+            .iter()
+            .all(|slot| slot.read(0).is_none() && slot.read(1).is_none()));
+    }
+}
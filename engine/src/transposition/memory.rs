@@ -0,0 +1,174 @@
+use std::alloc::{self, Layout};
+use std::fmt::{self, Display};
+use std::ops::Deref;
+use std::ptr::NonNull;
+
+use super::Bucket;
+
+/// Size of a transparent huge page on x86_64 and aarch64 Linux
+#[cfg(all(feature = "huge-pages", target_os = "linux"))]
+const HUGE_PAGE_SIZE: usize = 2 * 0x100000;
+
+/// Kind of pages backing the transposition table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageKind {
+    Regular,
+    /// Transparent huge pages, which need far fewer TLB entries for large tables
+    Huge,
+}
+
+impl Display for PageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageKind::Regular => write!(f, "regular pages"),
+            PageKind::Huge => write!(f, "transparent huge pages"),
+        }
+    }
+}
+
+enum Allocation {
+    Heap {
+        base: NonNull<u8>,
+        layout: Layout,
+    },
+    #[cfg(all(feature = "huge-pages", target_os = "linux"))]
+    Mapped {
+        base: NonNull<u8>,
+        size: usize,
+    },
+}
+
+/// Zeroed memory holding the buckets.
+///
+/// The standard allocator writes the zeros itself for alignments above the one of `malloc`, which
+/// takes seconds for tables of several GB. Instead the memory is requested with a small alignment,
+/// which lets the allocator use `calloc` and get zeroed pages from the operating system on first
+/// access, and the buckets are aligned within it.
+///
+/// With the `huge-pages` feature on Linux the memory is mapped directly, aligned to 2 MB and
+/// advised to be backed by transparent huge pages.
+pub(super) struct BucketMemory {
+    buckets: NonNull<Bucket>,
+    len: usize,
+    allocation: Allocation,
+    page_kind: PageKind,
+}
+
+// SAFETY: the memory is owned and the buckets only consist of atomics
+unsafe impl Send for BucketMemory {}
+unsafe impl Sync for BucketMemory {}
+
+impl BucketMemory {
+    /// Returns `None` if the memory could not be allocated
+    pub(super) fn zeroed(len: usize) -> Option<Self> {
+        let size = len.max(1).checked_mul(std::mem::size_of::<Bucket>())?;
+
+        #[cfg(all(feature = "huge-pages", target_os = "linux"))]
+        if let Some(memory) = Self::mapped(len, size) {
+            return Some(memory);
+        }
+
+        Self::heap(len, size)
+    }
+
+    pub(super) fn page_kind(&self) -> PageKind {
+        self.page_kind
+    }
+
+    fn heap(len: usize, size: usize) -> Option<Self> {
+        let align = std::mem::align_of::<Bucket>();
+        let layout =
+            Layout::from_size_align(size.checked_add(align)?, std::mem::align_of::<u64>()).ok()?;
+
+        // SAFETY: the layout has a non-zero size
+        let base = NonNull::new(unsafe { alloc::alloc_zeroed(layout) })?;
+
+        let offset = base.as_ptr().align_offset(align);
+        assert!(offset < align);
+
+        // SAFETY: the offset is smaller than the extra space allocated for the alignment, and
+        // zeroed memory is a valid empty bucket
+        let buckets = unsafe { NonNull::new_unchecked(base.as_ptr().add(offset) as *mut Bucket) };
+
+        Some(Self {
+            buckets,
+            len,
+            allocation: Allocation::Heap { base, layout },
+            page_kind: PageKind::Regular,
+        })
+    }
+
+    /// Maps anonymous memory, which the kernel provides zeroed. Huge pages are only used for
+    /// 2 MB aligned regions, so an extra huge page is mapped to align the buckets within it.
+    #[cfg(all(feature = "huge-pages", target_os = "linux"))]
+    fn mapped(len: usize, size: usize) -> Option<Self> {
+        let size = size.checked_next_multiple_of(HUGE_PAGE_SIZE)?;
+        let mapped_size = size.checked_add(HUGE_PAGE_SIZE)?;
+
+        // SAFETY: an anonymous private mapping doesn't alias any other memory
+        let base = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                mapped_size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+
+        if base == libc::MAP_FAILED {
+            return None;
+        }
+
+        let base = NonNull::new(base as *mut u8)?;
+        let offset = base.as_ptr().align_offset(HUGE_PAGE_SIZE);
+        assert!(offset < HUGE_PAGE_SIZE);
+
+        // SAFETY: the offset is smaller than the extra huge page that was mapped
+        let buckets = unsafe { base.as_ptr().add(offset) };
+
+        // the kernel may have transparent huge pages disabled, then regular pages are used
+        // SAFETY: the advised region lies within the mapping
+        let advised =
+            unsafe { libc::madvise(buckets as *mut libc::c_void, size, libc::MADV_HUGEPAGE) } == 0;
+
+        Some(Self {
+            // SAFETY: derived from a non-null pointer
+            buckets: unsafe { NonNull::new_unchecked(buckets as *mut Bucket) },
+            len,
+            allocation: Allocation::Mapped {
+                base,
+                size: mapped_size,
+            },
+            page_kind: if advised {
+                PageKind::Huge
+            } else {
+                PageKind::Regular
+            },
+        })
+    }
+}
+
+impl Deref for BucketMemory {
+    type Target = [Bucket];
+
+    fn deref(&self) -> &[Bucket] {
+        // SAFETY: the buckets are initialized and live as long as the allocation
+        unsafe { std::slice::from_raw_parts(self.buckets.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for BucketMemory {
+    fn drop(&mut self) {
+        match self.allocation {
+            // SAFETY: the memory was allocated with this layout
+            Allocation::Heap { base, layout } => unsafe { alloc::dealloc(base.as_ptr(), layout) },
+            // SAFETY: the whole mapping is unmapped once
+            #[cfg(all(feature = "huge-pages", target_os = "linux"))]
+            Allocation::Mapped { base, size } => unsafe {
+                libc::munmap(base.as_ptr() as *mut libc::c_void, size);
+            },
+        }
+    }
+}
@@ -35,6 +35,15 @@ impl Move {
         Move(((flag as u16) << 12) | ((to as u16) << 6) | (from as u16))
     }
 
+    /// Raw representation, e.g. for packing the move into a transposition table entry
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Move(bits)
+    }
+
     pub const fn from(&self) -> Square {
         Square::from_index((self.0 & 0x3f) as u8)
     }
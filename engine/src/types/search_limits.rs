@@ -10,6 +10,8 @@ pub struct SearchLimits {
     pub mate: Option<u8>,
     pub nodes: Option<u64>,
     pub search_moves: Vec<UCIMove>,
+    /// Search in the opponent's time until `ponderhit` or `stop` arrives
+    pub ponder: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
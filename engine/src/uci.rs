@@ -1,6 +1,11 @@
 use crate::board::Board;
 use crate::clock::Clock;
+use crate::evaluation::hce;
+use crate::evaluation::nnue::accumulator::AccumulatorStack;
+use crate::evaluation::nnue::{EMBEDDED_NETWORK_NAME, NNUE};
 use crate::movegen::perf_test;
+use crate::search::reductions::{Reductions, DEFAULT_LMR_BASE, DEFAULT_LMR_DIVISOR};
+use crate::search::{SearchOptions, DEFAULT_MULTI_PV};
 use crate::threadpool::ThreadPool;
 use crate::transposition::TranspositionTable;
 use crate::types::color::Color;
@@ -31,6 +36,11 @@ pub struct EngineUCI<S: ThreadSpawner, P: Printer> {
     engine_tx: Sender<EngineMessage>,
     threadpool: ThreadPool<S>,
     transposition_table: Arc<TranspositionTable>,
+    search_options: SearchOptions,
+    /// Name of the loaded network as given by the `EvalFile` option
+    eval_file: String,
+    /// Time limit of the current ponder search that takes effect on `ponderhit`
+    ponder_time: Option<TimeLimit>,
     ignore_commands: bool,
     _marker: PhantomData<P>,
 }
@@ -43,7 +53,11 @@ enum Command {
     SetOption { name: String, value: Option<String> },
     Position(StartingPosition, Vec<UCIMove>),
     Go(SearchLimits),
+    PonderHit,
     Perft { depth: u8 },
+    SaveHash { path: String },
+    LoadHash { path: String },
+    Eval,
     Debug,
     Stop,
     Quit,
@@ -58,6 +72,10 @@ enum ParseCommandError {
     InvalidNumber,
 }
 
+// The size difference is harmless: a starting position only exists between parsing a
+// `position` command and moving its board into the engine, it is never stored or copied around.
+// Boxing would add a heap allocation per command just to move the board out again.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, PartialEq)]
 enum StartingPosition {
     Standard,
@@ -66,11 +84,16 @@ enum StartingPosition {
 
 impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
     pub fn new(engine_tx: Sender<EngineMessage>) -> Self {
+        let transposition_table = Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE));
+
         Self {
             board: Default::default(),
             engine_tx,
             threadpool: ThreadPool::<S>::new(DEFAULT_THREADS),
-            transposition_table: Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE)),
+            transposition_table,
+            search_options: SearchOptions::default(),
+            eval_file: EMBEDDED_NETWORK_NAME.to_string(),
+            ponder_time: None,
             ignore_commands: false,
             _marker: Default::default(),
         }
@@ -113,7 +136,15 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
             "ucinewgame" => Command::NewGame,
             "position" => parse_position(parts)?,
             "go" => parse_go(parts)?,
+            "ponderhit" => Command::PonderHit,
             "perft" => parse_perft(parts)?,
+            "savehash" => Command::SaveHash {
+                path: parse_path(parts)?,
+            },
+            "loadhash" => Command::LoadHash {
+                path: parse_path(parts)?,
+            },
+            "eval" => Command::Eval,
             "debug" => Command::Debug,
             "quit" => Command::Quit,
             "stop" => Command::Stop,
@@ -136,7 +167,29 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
                 P::println(&format!(
                     "option name Threads type spin default {DEFAULT_THREADS} min 1 max 255"
                 ));
+
+                P::println(&format!(
+                    "option name LMRBase type spin default {DEFAULT_LMR_BASE} min 0 max 500"
+                ));
+
+                P::println(&format!(
+                    "option name LMRDivisor type spin default {DEFAULT_LMR_DIVISOR} min 50 max 1000"
+                ));
+
+                P::println(&format!(
+                    "option name MultiPV type spin default {DEFAULT_MULTI_PV} min 1 max 255"
+                ));
+
+                P::println("option name Ponder type check default false");
+                P::println("option name Clear Hash type button");
+                P::println(&format!(
+                    "option name EvalFile type string default {EMBEDDED_NETWORK_NAME}"
+                ));
+                P::println("option name UseNNUE type check default false");
                 P::println("uciok");
+
+                // info strings are only read by the GUI once the handshake is done
+                report_hash_allocation::<P>(&self.transposition_table);
             }
             Command::IsReady => {
                 P::println("readyok");
@@ -150,12 +203,72 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
                     }
                 }
                 "Hash" => {
-                    if let Some(size_mb) = value.and_then(|v| v.parse::<usize>().ok()) {
+                    if self.threadpool.is_searching() {
+                        P::println("info string cannot resize the hash during a search");
+                    } else if let Some(size_mb) = value.and_then(|v| v.parse::<usize>().ok()) {
                         self.transposition_table = Arc::new(TranspositionTable::new(size_mb));
+                        report_hash_allocation::<P>(&self.transposition_table);
+                        // the new memory is already zeroed, but clearing it with all threads
+                        // commits its pages in parallel instead of during the next search
+                        self.threadpool.clear(self.transposition_table.clone());
                     } else {
                         eprintln!("invalid value");
                     }
                 }
+                "LMRBase" | "LMRDivisor" => {
+                    if let Some(param) = value.and_then(|v| v.parse::<u16>().ok()) {
+                        let reductions = &self.search_options.reductions;
+                        let (base, divisor) = match name.as_str() {
+                            "LMRBase" => (param, reductions.divisor()),
+                            "LMRDivisor" => (reductions.base(), param),
+                            _ => unreachable!(),
+                        };
+
+                        self.search_options.reductions = Arc::new(Reductions::new(base, divisor));
+                    } else {
+                        eprintln!("invalid value");
+                    }
+                }
+                "MultiPV" => {
+                    if let Some(multi_pv) = value.and_then(|v| v.parse::<u8>().ok()) {
+                        self.search_options.multi_pv = multi_pv.max(1);
+                    } else {
+                        eprintln!("invalid value");
+                    }
+                }
+                "EvalFile" => {
+                    let Some(path) = value else {
+                        eprintln!("invalid value");
+                        return;
+                    };
+
+                    let network = if path == EMBEDDED_NETWORK_NAME {
+                        Ok(NNUE::embedded())
+                    } else {
+                        NNUE::load(&path)
+                    };
+
+                    match network {
+                        Ok(network) => {
+                            self.search_options.network = Arc::from(network);
+                            P::println(&format!("info string loaded network {path}"));
+                            self.eval_file = path;
+                        }
+                        Err(err) => {
+                            P::println(&format!("info string failed to load network {path}: {err}"))
+                        }
+                    }
+                }
+                "UseNNUE" => match value.as_deref() {
+                    Some("true") => self.search_options.use_nnue = true,
+                    Some("false") => self.search_options.use_nnue = false,
+                    _ => eprintln!("invalid value"),
+                },
+                "Clear Hash" => {
+                    self.threadpool.clear(self.transposition_table.clone());
+                }
+                // the GUI decides when to ponder, the option only tells it that we can
+                "Ponder" => {}
                 _ => eprintln!("invalid option"),
             },
             Command::NewGame => {
@@ -179,21 +292,57 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
                 self.board = board;
             }
             Command::Go(limits) => {
+                // a ponder search runs without time limits until the opponent made the
+                // expected move
+                let time = if limits.ponder {
+                    self.ponder_time = Some(limits.time.clone());
+                    TimeLimit::Infinite
+                } else {
+                    self.ponder_time = None;
+                    limits.time.clone()
+                };
+
                 // The clock should be started as soon as possible even if the search has to wait in queue
-                let clock = Clock::new(
-                    &limits.time,
-                    self.board.game_ply(),
-                    self.board.side_to_move(),
-                );
+                let clock = Clock::new(&time, self.board.game_ply(), self.board.side_to_move());
 
                 self.threadpool.search(
                     self.board.clone(),
                     limits,
                     clock,
+                    self.search_options.clone(),
                     self.engine_tx.clone(),
                     self.transposition_table.clone(),
                 );
             }
+            Command::PonderHit => {
+                if let Some(time) = self.ponder_time.take() {
+                    // the clock starts now as the opponent just made the move
+                    let clock = Clock::new(&time, self.board.game_ply(), self.board.side_to_move());
+                    self.threadpool.ponderhit(&time, clock);
+                }
+            }
+            Command::Eval => {
+                P::println(hce::trace(&self.board).to_string().as_str());
+
+                // the placeholder network evaluates every position as equal
+                if self.eval_file == EMBEDDED_NETWORK_NAME && NNUE::embedded_is_placeholder() {
+                    P::println("NNUE evaluation: none (no network embedded, see EvalFile)");
+                    return;
+                }
+
+                let side_to_move = self.board.side_to_move();
+                let accumulators =
+                    AccumulatorStack::new(self.search_options.network.clone(), &self.board);
+                let evaluation = match side_to_move {
+                    Color::White => accumulators.evaluate(side_to_move),
+                    Color::Black => -accumulators.evaluate(side_to_move),
+                };
+                P::println(&format!(
+                    "NNUE evaluation: {:+.2} (white side, network {})",
+                    evaluation as f64 / 100.0,
+                    self.eval_file
+                ));
+            }
             Command::Debug => {
                 P::println(self.board.to_string().as_str());
             }
@@ -207,10 +356,32 @@ impl<S: ThreadSpawner, P: Printer> EngineUCI<S, P> {
             Command::Perft { depth } => {
                 perf_test::<P>(&mut self.board, depth);
             }
+            Command::SaveHash { path } => match self.transposition_table.save(&path) {
+                Ok(()) => P::println(&format!("info string saved hash to {path}")),
+                Err(err) => P::println(&format!("info string failed to save hash: {err}")),
+            },
+            Command::LoadHash { path } => match TranspositionTable::load(&path) {
+                Ok(tt) => {
+                    P::println(&format!(
+                        "info string loaded {} MB hash from {path}",
+                        tt.size_mb()
+                    ));
+                    self.transposition_table = Arc::new(tt);
+                }
+                Err(err) => P::println(&format!("info string failed to load hash: {err}")),
+            },
         }
     }
 }
 
+fn report_hash_allocation<P: Printer>(tt: &TranspositionTable) {
+    P::println(&format!(
+        "info string hash of {} MB allocated with {}",
+        tt.size_mb(),
+        tt.page_kind()
+    ));
+}
+
 fn parse_perft(
     mut parts: Peekable<SplitAsciiWhitespace<'_>>,
 ) -> Result<Command, ParseCommandError> {
@@ -223,6 +394,16 @@ fn parse_perft(
     })
 }
 
+/// File paths are the rest of the command and can include spaces
+fn parse_path(parts: Peekable<SplitAsciiWhitespace<'_>>) -> Result<String, ParseCommandError> {
+    let path = parts.collect::<Vec<_>>().join(" ");
+    if path.is_empty() {
+        return Err(ParseCommandError::MissingParts);
+    }
+
+    Ok(path)
+}
+
 fn parse_setoption(
     mut parts: Peekable<SplitAsciiWhitespace<'_>>,
 ) -> Result<Command, ParseCommandError> {
@@ -271,12 +452,16 @@ fn parse_go(mut parts: Peekable<SplitAsciiWhitespace<'_>>) -> Result<Command, Pa
     let mut moves_to_go: Option<u8> = None;
     let mut nodes: Option<u64> = None;
     let mut infinite = false;
+    let mut ponder = false;
     let mut search_moves = vec![];
     while let Some(token) = parts.next() {
         match token {
             "infinite" => {
                 infinite = true;
             }
+            "ponder" => {
+                ponder = true;
+            }
             "wtime" | "btime" | "winc" | "binc" | "movetime" => {
                 let param = parts
                     .next()
@@ -347,6 +532,7 @@ fn parse_go(mut parts: Peekable<SplitAsciiWhitespace<'_>>) -> Result<Command, Pa
         mate,
         nodes,
         search_moves,
+        ponder,
     };
     Ok(Command::Go(limits))
 }
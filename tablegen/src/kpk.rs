@@ -0,0 +1,227 @@
+use crate::BitBoard;
+use crate::Color;
+use crate::king_move::generate_king_attacks;
+use crate::pawn_move::generate_pawn_attacks;
+
+/// Positions with white king and pawn against the black king. The pawn is on files a-d and ranks
+/// 2-7, positions with the pawn on files e-h are looked up mirrored.
+pub const KPK_POSITIONS: usize = 24 * 2 * 64 * 64;
+/// Number of words of the bitbase, one bit per position that is won for white
+pub const KPK_BITBASE_SIZE: usize = KPK_POSITIONS / 64;
+
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+/// Index of a position in the bitbase, the pawn has to be on files a-d and ranks 2-7
+pub const fn kpk_index(side_to_move: Color, white_king: u8, black_king: u8, pawn: u8) -> usize {
+    let pawn = (pawn / 8 - 1) * 4 + pawn % 8;
+
+    ((pawn as usize * 2 + side_to_move as usize) * 64 + white_king as usize) * 64
+        + black_king as usize
+}
+
+struct Position {
+    side_to_move: Color,
+    white_king: u8,
+    black_king: u8,
+    pawn: u8,
+}
+
+impl Position {
+    const fn from_index(index: usize) -> Position {
+        let pawn = (index / (2 * 64 * 64)) as u8;
+
+        Position {
+            side_to_move: if (index / (64 * 64)).is_multiple_of(2) {
+                Color::White
+            } else {
+                Color::Black
+            },
+            white_king: ((index / 64) % 64) as u8,
+            black_king: (index % 64) as u8,
+            pawn: (pawn / 4 + 1) * 8 + pawn % 4,
+        }
+    }
+}
+
+const fn contains(bitboard: BitBoard, square: u8) -> bool {
+    bitboard.0 & (1 << square) != 0
+}
+
+const fn distance(a: u8, b: u8) -> u8 {
+    let files = (a % 8).abs_diff(b % 8);
+    let ranks = (a / 8).abs_diff(b / 8);
+
+    if files > ranks { files } else { ranks }
+}
+
+/// Generates the KPK bitbase by retrograde analysis: positions are classified by the rules
+/// until no unknown position can be resolved anymore, the remaining ones are draws
+pub fn generate_kpk_bitbase() -> [u64; KPK_BITBASE_SIZE] {
+    let king_attacks = generate_king_attacks();
+    let pawn_attacks = generate_pawn_attacks()[Color::White as usize];
+
+    let mut results = vec![INVALID; KPK_POSITIONS];
+    for (index, result) in results.iter_mut().enumerate() {
+        *result = classify_initial(&Position::from_index(index), &king_attacks, &pawn_attacks);
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for index in 0..KPK_POSITIONS {
+            if results[index] == UNKNOWN {
+                let position = Position::from_index(index);
+                results[index] = classify(&position, &results, &king_attacks);
+                changed |= results[index] != UNKNOWN;
+            }
+        }
+    }
+
+    let mut bitbase = [0; KPK_BITBASE_SIZE];
+    for (index, &result) in results.iter().enumerate() {
+        if result == WIN {
+            bitbase[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    bitbase
+}
+
+fn classify_initial(
+    position: &Position,
+    king_attacks: &[BitBoard; 64],
+    pawn_attacks: &[BitBoard; 64],
+) -> u8 {
+    let Position {
+        side_to_move,
+        white_king,
+        black_king,
+        pawn,
+    } = *position;
+    let push = pawn + 8;
+
+    if distance(white_king, black_king) <= 1
+        || white_king == pawn
+        || black_king == pawn
+        || (side_to_move == Color::White && contains(pawn_attacks[pawn as usize], black_king))
+    {
+        return INVALID;
+    }
+
+    match side_to_move {
+        // the pawn promotes and the queen can't be captured
+        Color::White
+            if pawn / 8 == 6
+                && white_king != push
+                && (distance(black_king, push) > 1
+                    || contains(king_attacks[white_king as usize], push)) =>
+        {
+            WIN
+        }
+        Color::Black => {
+            let defended = king_attacks[white_king as usize].0 | pawn_attacks[pawn as usize].0;
+            let moves = king_attacks[black_king as usize].0;
+
+            // stalemate or the pawn can be captured
+            if moves & !defended == 0 || contains(BitBoard(moves & !defended), pawn) {
+                DRAW
+            } else {
+                UNKNOWN
+            }
+        }
+        _ => UNKNOWN,
+    }
+}
+
+/// A position is won if white can move into a won position and drawn if black can move into a
+/// drawn position. Moves to invalid positions are illegal and contribute nothing.
+fn classify(position: &Position, results: &[u8], king_attacks: &[BitBoard; 64]) -> u8 {
+    let Position {
+        side_to_move,
+        white_king,
+        black_king,
+        pawn,
+    } = *position;
+
+    let mut successors = INVALID;
+
+    match side_to_move {
+        Color::White => {
+            let mut moves = king_attacks[white_king as usize].0;
+            while moves != 0 {
+                let to = moves.trailing_zeros() as u8;
+                successors |= results[kpk_index(Color::Black, to, black_king, pawn)];
+                moves &= moves - 1;
+            }
+
+            let push = pawn + 8;
+            if pawn / 8 < 6 {
+                successors |= results[kpk_index(Color::Black, white_king, black_king, push)];
+            }
+            if pawn / 8 == 1 && push != white_king && push != black_king {
+                successors |= results[kpk_index(Color::Black, white_king, black_king, push + 8)];
+            }
+        }
+        Color::Black => {
+            let mut moves = king_attacks[black_king as usize].0;
+            while moves != 0 {
+                let to = moves.trailing_zeros() as u8;
+                successors |= results[kpk_index(Color::White, white_king, to, pawn)];
+                moves &= moves - 1;
+            }
+        }
+    }
+
+    let (good, bad) = match side_to_move {
+        Color::White => (WIN, DRAW),
+        Color::Black => (DRAW, WIN),
+    };
+
+    if successors & good != 0 {
+        good
+    } else if successors & UNKNOWN != 0 {
+        UNKNOWN
+    } else {
+        bad
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Color;
+    use crate::Square;
+    use crate::kpk::{generate_kpk_bitbase, kpk_index};
+
+    #[test]
+    fn test_kpk_bitbase() {
+        let bitbase = generate_kpk_bitbase();
+        let is_win = |side_to_move, white_king: Square, black_king: Square, pawn: Square| {
+            let index = kpk_index(
+                side_to_move,
+                white_king.to_index(),
+                black_king.to_index(),
+                pawn.to_index(),
+            );
+            bitbase[index / 64] & (1 << (index % 64)) != 0
+        };
+
+        // king on a key square in front of the pawn
+        assert!(is_win(Color::White, Square::C6, Square::C8, Square::C5));
+        assert!(is_win(Color::Black, Square::D6, Square::D8, Square::D5));
+        // opposition
+        assert!(!is_win(Color::White, Square::D5, Square::D7, Square::D4));
+        assert!(is_win(Color::Black, Square::D5, Square::D7, Square::D4));
+        // rook pawns are drawn once the defending king reaches the corner
+        assert!(!is_win(Color::White, Square::B6, Square::A8, Square::A5));
+        // the pawn outruns the king
+        assert!(is_win(Color::White, Square::H1, Square::H8, Square::A4));
+        assert!(!is_win(Color::White, Square::H1, Square::B8, Square::A4));
+
+        let wins: usize = bitbase.iter().map(|word| word.count_ones() as usize).sum();
+        assert_eq!(wins, 111282);
+    }
+}
@@ -5,6 +5,7 @@ use std::{
 
 pub mod king_move;
 pub mod knight_move;
+pub mod kpk;
 pub mod magics;
 pub mod pawn_move;
 pub mod rays_between;
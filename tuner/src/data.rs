@@ -0,0 +1,316 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Context};
+use bulletformat::{BulletFormat, ChessBoard, DataLoader};
+use engine::{
+    board::Board,
+    evaluation::{
+        endgame::{self, MaterialKey},
+        hce,
+        params::{EvalParams, PARAMS},
+        pawns::PawnTable,
+        score::Score,
+        trace::Tracer,
+    },
+    types::color::Color,
+};
+
+/// How often a weight is applied in a position, white's applications count positive and black's
+/// negative
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Feature {
+    pub index: u16,
+    pub count: i8,
+}
+
+/// A labelled position reduced to what the tuner needs. The evaluation is linear in the weights,
+/// so it is fully described by the applied weights, the game phase and the endgame scale factor.
+#[derive(Debug)]
+pub struct Position {
+    pub features: Box<[Feature]>,
+    pub phase: u8,
+    /// Scale factor of the endgame score for the default weights, it is kept fixed while tuning
+    pub scale: u8,
+    /// Game result from white's point of view, 1.0 for a win and 0.0 for a loss
+    pub result: f32,
+}
+
+/// Counts the weights applied by the evaluation
+struct Coefficients {
+    counts: Vec<i32>,
+}
+
+impl Tracer for Coefficients {
+    const ENABLED: bool = true;
+
+    fn record(&mut self, weight: &'static Score, color: Color) {
+        self.counts[PARAMS.index_of(weight)] += match color {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+    }
+}
+
+/// Turns boards into positions, reusing the buffers between positions
+pub struct Extractor {
+    coefficients: Coefficients,
+    pawn_table: PawnTable,
+}
+
+impl Extractor {
+    pub fn new() -> Self {
+        Self {
+            coefficients: Coefficients {
+                counts: vec![0; EvalParams::LEN],
+            },
+            pawn_table: PawnTable::new(),
+        }
+    }
+
+    /// Known endgames are skipped, their evaluation doesn't depend on the weights
+    pub fn extract(&mut self, board: &Board, result: f32) -> Option<Position> {
+        let key = MaterialKey::new(board);
+        if endgame::evaluate(board, key).is_some() {
+            return None;
+        }
+
+        hce::evaluate(board, &mut self.pawn_table, &mut self.coefficients);
+
+        let mut score = Score::ZERO;
+        let features: Box<[Feature]> = self
+            .coefficients
+            .counts
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, count)| **count != 0)
+            .map(|(index, count)| {
+                score += PARAMS.weights()[index] * *count;
+
+                let feature = Feature {
+                    index: index as u16,
+                    count: *count as i8,
+                };
+                *count = 0;
+                feature
+            })
+            .collect();
+
+        Some(Position {
+            features,
+            phase: hce::game_phase(board) as u8,
+            scale: endgame::scale_factor(board, key, score) as u8,
+            result,
+        })
+    }
+}
+
+impl Default for Extractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Loads the positions of a file, `.bin` files are read as bulletformat chess boards and
+/// everything else as text with one labelled position per line
+pub fn load(path: &Path, extractor: &mut Extractor) -> anyhow::Result<Vec<Position>> {
+    if path.extension().is_some_and(|extension| extension == "bin") {
+        load_bulletformat(path, extractor)
+    } else {
+        load_text(path, extractor)
+    }
+}
+
+fn load_text(path: &Path, extractor: &mut Extractor) -> anyhow::Result<Vec<Position>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut positions = vec![];
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context("Failed to read line")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (fen, result) = parse_line(&line)
+            .with_context(|| format!("{}:{}: invalid position", path.display(), number + 1))?;
+        let board = Board::from_str(&fen)
+            .map_err(|err| anyhow!("{}:{}: invalid FEN: {err:?}", path.display(), number + 1))?;
+
+        positions.extend(extractor.extract(&board, result));
+    }
+
+    Ok(positions)
+}
+
+fn load_bulletformat(path: &Path, extractor: &mut Extractor) -> anyhow::Result<Vec<Position>> {
+    let loader = DataLoader::<ChessBoard>::new(path, 256)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut positions = Vec::with_capacity(loader.len());
+    let mut error = None;
+
+    loader.map_positions(|entry| {
+        match Board::from_str(&bulletformat_fen(entry)) {
+            // the board is stored from the point of view of the side to move, which becomes white
+            Ok(board) => positions.extend(extractor.extract(&board, entry.result())),
+            Err(err) => {
+                error.get_or_insert(err);
+            }
+        }
+    });
+
+    match error {
+        Some(err) => bail!("{}: invalid board: {err:?}", path.display()),
+        None => Ok(positions),
+    }
+}
+
+/// FEN of a bulletformat board with the side to move playing white
+fn bulletformat_fen(board: &ChessBoard) -> String {
+    let mut squares = [None; 64];
+    for (piece, square) in *board {
+        squares[square as usize] = Some(piece);
+    }
+
+    let mut placement = String::new();
+    for rank in (0..8).rev() {
+        let mut empty = 0;
+        for file in 0..8 {
+            let Some(piece) = squares[rank * 8 + file] else {
+                empty += 1;
+                continue;
+            };
+
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+                empty = 0;
+            }
+
+            let symbol = b"PNBRQK"[(piece & 0b111) as usize] as char;
+            placement.push(if piece & 0b1000 == 0 {
+                symbol
+            } else {
+                symbol.to_ascii_lowercase()
+            });
+        }
+
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+        if rank > 0 {
+            placement.push('/');
+        }
+    }
+
+    format!("{placement} w - - 0 1")
+}
+
+/// Splits a line into the FEN and the game result from white's point of view. Understands
+/// - EPD with the result as `1-0`, `0-1` or `1/2-1/2`, e.g. in a `c9` operation
+/// - FEN followed by the result in brackets, e.g. `[0.5]`
+/// - the bulletformat text format `<FEN> | <score> | <result>`
+fn parse_line(line: &str) -> anyhow::Result<(String, f32)> {
+    let (position, label) = match line.split_once('|') {
+        Some((position, rest)) => (position, rest.rsplit('|').next().unwrap_or(rest)),
+        None => match line.split_once('[') {
+            Some((position, rest)) => (position, rest.trim_end().trim_end_matches(']')),
+            None => (line, ""),
+        },
+    };
+
+    let fields: Vec<_> = position.split_whitespace().collect();
+    if fields.len() < 4 {
+        bail!("expected at least four FEN fields");
+    }
+
+    // EPD has no move counters, which don't matter for the evaluation anyway
+    let counters = match fields.get(4..6) {
+        Some(counters) if counters.iter().all(|field| field.parse::<u32>().is_ok()) => {
+            counters.join(" ")
+        }
+        _ => String::from("0 1"),
+    };
+    let fen = format!("{} {counters}", fields[..4].join(" "));
+
+    let result = if !label.is_empty() {
+        label.trim().parse().context("invalid result")?
+    } else {
+        let operations = fields[4..].join(" ");
+        if operations.contains("1/2-1/2") {
+            0.5
+        } else if operations.contains("1-0") {
+            1.0
+        } else if operations.contains("0-1") {
+            0.0
+        } else {
+            bail!("missing game result");
+        }
+    };
+
+    if !(0.0..=1.0).contains(&result) {
+        bail!("result {result} is not between 0 and 1");
+    }
+
+    Ok((fen, result))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bulletformat::{BulletFormat, ChessBoard};
+    use engine::{board::Board, evaluation::endgame};
+
+    use crate::data::{bulletformat_fen, parse_line, Extractor};
+
+    #[test]
+    fn test_parse_line() {
+        let (fen, result) =
+            parse_line("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - c9 \"1/2-1/2\";")
+                .unwrap();
+        assert_eq!(
+            fen,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert_eq!(result, 0.5);
+
+        let (fen, result) = parse_line("8/8/4k3/8/8/4K3/4P3/8 w - - 3 40 [1.0]").unwrap();
+        assert_eq!(fen, "8/8/4k3/8/8/4K3/4P3/8 w - - 3 40");
+        assert_eq!(result, 1.0);
+
+        let (_, result) = parse_line("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1 | -120 | 0.0").unwrap();
+        assert_eq!(result, 0.0);
+
+        assert!(parse_line("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1").is_err());
+        assert!(parse_line("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1 [2.0]").is_err());
+    }
+
+    #[test]
+    fn test_bulletformat_perspective() {
+        // black to move is mirrored vertically, so black's pieces become white's
+        let board = ChessBoard::from_str("4k3/8/8/8/8/8/3q4/4K3 b - - 0 1 | 500 | 0.0").unwrap();
+
+        assert_eq!(bulletformat_fen(&board), "4k3/3Q4/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(board.result(), 1.0);
+    }
+
+    #[test]
+    fn test_features_are_symmetric() {
+        let mut extractor = Extractor::new();
+        let board =
+            Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4")
+                .unwrap();
+        let position = extractor.extract(&board, 0.5).unwrap();
+
+        // every weight applied for white is applied for black as well
+        assert!(position.features.is_empty());
+        assert_eq!(position.phase, 24);
+        assert_eq!(position.scale as i32, endgame::NORMAL_SCALE);
+
+        let known = Board::from_str("8/8/3k4/8/8/2NBK3/8/8 w - - 0 1").unwrap();
+        assert!(extractor.extract(&known, 1.0).is_none());
+    }
+}
@@ -0,0 +1,103 @@
+mod data;
+mod tune;
+
+use std::{fs, path::PathBuf, thread, time::Instant};
+
+use anyhow::Context;
+use clap::Parser;
+use engine::evaluation::params::{EvalParams, DEFAULT_PARAMS};
+
+use crate::{
+    data::Extractor,
+    tune::{Adam, Weights},
+};
+
+/// Tunes the weights of the hand-crafted evaluation on labelled positions by minimising the error
+/// between the game results and the results the evaluation predicts.
+///
+/// The data should consist of quiet positions, because the evaluation is used without a
+/// quiescence search.
+#[derive(Debug, Parser)]
+struct Cli {
+    #[arg(short, long)]
+    threads: Option<u8>,
+    /// Number of gradient descent steps over the whole data
+    #[arg(short, long, default_value_t = 1000)]
+    epochs: u32,
+    #[arg(short, long, default_value_t = 1.0)]
+    learning_rate: f64,
+    /// Where to write the tuned weights, the file replaces
+    /// `engine/src/evaluation/params/default.rs`
+    #[arg(short, long, default_value = "default.rs")]
+    output: PathBuf,
+
+    /// EPD files with game results, text files with one `<FEN> | <score> | <result>` per line or
+    /// bulletformat `.bin` files as written by datagen
+    #[arg(required = true)]
+    data: Vec<PathBuf>,
+}
+
+/// Epochs between progress reports, the current weights are saved as well
+const REPORT_INTERVAL: u32 = 50;
+
+fn save(cli: &Cli, weights: &Weights) -> anyhow::Result<EvalParams> {
+    let params = tune::params_with(&DEFAULT_PARAMS, weights);
+    fs::write(&cli.output, params.to_source()).context("Failed to write tuned weights")?;
+    Ok(params)
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let threads = match cli.threads {
+        Some(threads) => threads as usize,
+        None => thread::available_parallelism().map_or(1, |threads| threads.get()),
+    };
+
+    let mut extractor = Extractor::new();
+    let mut positions = vec![];
+    for path in &cli.data {
+        let loaded = data::load(path, &mut extractor)?;
+        println!("Loaded {} positions from {}", loaded.len(), path.display());
+        positions.extend(loaded);
+    }
+    anyhow::ensure!(!positions.is_empty(), "No positions to tune on");
+
+    let mut weights = tune::weights_of(&DEFAULT_PARAMS);
+    let scaling = tune::fit_scaling(&positions, &weights, threads);
+    println!(
+        "Scaling {scaling:.6}, initial loss {:.6}",
+        tune::loss(&positions, &weights, scaling, threads)
+    );
+
+    let mut optimizer = Adam::new(cli.learning_rate, weights.len());
+    let start = Instant::now();
+
+    for epoch in 1..=cli.epochs {
+        let gradient = tune::gradient(&positions, &weights, scaling, threads);
+        optimizer.step(&mut weights, &gradient);
+
+        if epoch % REPORT_INTERVAL == 0 || epoch == cli.epochs {
+            save(&cli, &weights)?;
+            println!(
+                "Epoch {epoch}: loss {:.6} ({:.1}s)",
+                tune::loss(&positions, &weights, scaling, threads),
+                start.elapsed().as_secs_f64()
+            );
+        }
+    }
+
+    let params = save(&cli, &weights)?;
+    let changed = params
+        .weights()
+        .iter()
+        .zip(DEFAULT_PARAMS.weights())
+        .filter(|(tuned, default)| tuned != default)
+        .count();
+    println!(
+        "Changed {changed} of {} weights, written to {}",
+        EvalParams::LEN,
+        cli.output.display()
+    );
+
+    Ok(())
+}
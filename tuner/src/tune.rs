@@ -0,0 +1,264 @@
+use std::thread;
+
+use engine::evaluation::{endgame::NORMAL_SCALE, hce::MAX_PHASE, params::EvalParams, score::Score};
+
+use crate::data::Position;
+
+/// Middlegame and endgame value of every weight
+pub type Weights = Vec<[f64; 2]>;
+
+pub fn weights_of(params: &EvalParams) -> Weights {
+    params
+        .weights()
+        .iter()
+        .map(|weight| [weight.mg() as f64, weight.eg() as f64])
+        .collect()
+}
+
+/// Copies `params` with the weights replaced by the rounded tuned values
+pub fn params_with(params: &EvalParams, weights: &Weights) -> EvalParams {
+    let round = |value: f64| value.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+
+    let mut params = params.clone();
+    for (weight, &[mg, eg]) in params.weights_mut().iter_mut().zip(weights) {
+        *weight = Score::new(round(mg), round(eg));
+    }
+    params
+}
+
+/// Evaluation of the position in centipawns from white's point of view, the same as the engine's
+/// except for rounding
+pub fn evaluate(position: &Position, weights: &Weights) -> f64 {
+    let (mg, eg) = position
+        .features
+        .iter()
+        .fold((0.0, 0.0), |(mg, eg), feature| {
+            let [weight_mg, weight_eg] = weights[feature.index as usize];
+            let count = feature.count as f64;
+            (mg + count * weight_mg, eg + count * weight_eg)
+        });
+
+    let phase = position.phase as f64;
+    let eg = eg * position.scale as f64 / NORMAL_SCALE as f64;
+    (mg * phase + eg * (MAX_PHASE as f64 - phase)) / MAX_PHASE as f64
+}
+
+/// Expected game result for an evaluation, `scaling` converts centipawns to the sigmoid's domain
+fn sigmoid(evaluation: f64, scaling: f64) -> f64 {
+    1.0 / (1.0 + (-scaling * evaluation).exp())
+}
+
+/// Mean squared error between the expected and the actual game results
+pub fn loss(positions: &[Position], weights: &Weights, scaling: f64, threads: usize) -> f64 {
+    let errors = parallel(positions, threads, |chunk| {
+        chunk
+            .iter()
+            .map(|position| {
+                let error = sigmoid(evaluate(position, weights), scaling) - position.result as f64;
+                error * error
+            })
+            .sum::<f64>()
+    });
+
+    errors.into_iter().sum::<f64>() / positions.len() as f64
+}
+
+/// The scaling of the sigmoid that fits the current weights best, found by a golden section
+/// search. Tuning with it fixed keeps the weights in centipawns.
+pub fn fit_scaling(positions: &[Position], weights: &Weights, threads: usize) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.0, 0.05);
+
+    while high - low > 1e-7 {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+
+        if loss(positions, weights, left, threads) < loss(positions, weights, right, threads) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+/// Gradient of the loss with respect to every weight
+pub fn gradient(
+    positions: &[Position],
+    weights: &Weights,
+    scaling: f64,
+    threads: usize,
+) -> Weights {
+    let partials = parallel(positions, threads, |chunk| {
+        let mut gradient = vec![[0.0; 2]; weights.len()];
+
+        for position in chunk {
+            let expected = sigmoid(evaluate(position, weights), scaling);
+            let error = 2.0 * (expected - position.result as f64);
+            let derivative = error * expected * (1.0 - expected) * scaling;
+
+            let phase = position.phase as f64 / MAX_PHASE as f64;
+            let scale = position.scale as f64 / NORMAL_SCALE as f64;
+            let (mg, eg) = (derivative * phase, derivative * (1.0 - phase) * scale);
+
+            for feature in position.features.iter() {
+                let count = feature.count as f64;
+                let partial = &mut gradient[feature.index as usize];
+                partial[0] += count * mg;
+                partial[1] += count * eg;
+            }
+        }
+
+        gradient
+    });
+
+    let mut gradient = vec![[0.0; 2]; weights.len()];
+    for partial in partials {
+        for (total, value) in gradient.iter_mut().zip(partial) {
+            total[0] += value[0];
+            total[1] += value[1];
+        }
+    }
+
+    let len = positions.len() as f64;
+    for total in gradient.iter_mut() {
+        total[0] /= len;
+        total[1] /= len;
+    }
+
+    gradient
+}
+
+/// Gradient descent with per weight learning rates, see Kingma and Ba, "Adam: A Method for
+/// Stochastic Optimization"
+pub struct Adam {
+    learning_rate: f64,
+    momentum: Weights,
+    velocity: Weights,
+    steps: i32,
+}
+
+impl Adam {
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    const EPSILON: f64 = 1e-8;
+
+    pub fn new(learning_rate: f64, len: usize) -> Self {
+        Self {
+            learning_rate,
+            momentum: vec![[0.0; 2]; len],
+            velocity: vec![[0.0; 2]; len],
+            steps: 0,
+        }
+    }
+
+    pub fn step(&mut self, weights: &mut Weights, gradient: &Weights) {
+        self.steps += 1;
+        let momentum_correction = 1.0 - Self::BETA1.powi(self.steps);
+        let velocity_correction = 1.0 - Self::BETA2.powi(self.steps);
+
+        for (i, weight) in weights.iter_mut().enumerate() {
+            for phase in 0..2 {
+                let gradient = gradient[i][phase];
+                let momentum = &mut self.momentum[i][phase];
+                let velocity = &mut self.velocity[i][phase];
+
+                *momentum = Self::BETA1 * *momentum + (1.0 - Self::BETA1) * gradient;
+                *velocity = Self::BETA2 * *velocity + (1.0 - Self::BETA2) * gradient * gradient;
+
+                let momentum = *momentum / momentum_correction;
+                let velocity = *velocity / velocity_correction;
+                weight[phase] -= self.learning_rate * momentum / (velocity.sqrt() + Self::EPSILON);
+            }
+        }
+    }
+}
+
+/// Applies `f` to equal chunks of the positions on `threads` threads
+fn parallel<R: Send>(
+    positions: &[Position],
+    threads: usize,
+    f: impl Fn(&[Position]) -> R + Sync,
+) -> Vec<R> {
+    let chunk_size = positions.len().div_ceil(threads.max(1)).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = positions
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| f(chunk)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use engine::{
+        board::Board,
+        evaluation::{hce, params::DEFAULT_PARAMS, pawns::PawnTable},
+    };
+
+    use crate::data::{Extractor, Position};
+    use crate::tune::{evaluate, gradient, loss, params_with, weights_of, Adam};
+
+    const FENS: [&str; 5] = [
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+        "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8",
+        "8/5pk1/6p1/3P4/1r6/6P1/5PK1/3R4 w - - 0 40",
+        "6k1/1p3pp1/p6p/8/8/1P4QP/P4PP1/2q3K1 b - - 0 30",
+        "8/3kb3/8/3p4/2PP4/3B4/4K3/8 w - - 0 1",
+    ];
+
+    fn positions(result: f32) -> Vec<Position> {
+        let mut extractor = Extractor::new();
+        FENS.iter()
+            .map(|fen| {
+                extractor
+                    .extract(&Board::from_str(fen).unwrap(), result)
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluation_matches_engine() {
+        let weights = weights_of(&DEFAULT_PARAMS);
+
+        for (fen, position) in FENS.iter().zip(positions(0.5)) {
+            let board = Board::from_str(fen).unwrap();
+            let expected = hce::board_value(&board, &mut PawnTable::new()).val() as f64;
+
+            // the engine rounds down both the scaled endgame score and the tapered evaluation
+            assert!(
+                (evaluate(&position, &weights) - expected).abs() < 2.0,
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_descent_reduces_loss() {
+        let positions = positions(1.0);
+        let mut weights = weights_of(&DEFAULT_PARAMS);
+        let mut optimizer = Adam::new(1.0, weights.len());
+
+        let before = loss(&positions, &weights, 0.01, 2);
+        for _ in 0..10 {
+            let gradient = gradient(&positions, &weights, 0.01, 2);
+            optimizer.step(&mut weights, &gradient);
+        }
+
+        assert!(loss(&positions, &weights, 0.01, 2) < before);
+        assert_eq!(
+            params_with(&DEFAULT_PARAMS, &weights_of(&DEFAULT_PARAMS)),
+            DEFAULT_PARAMS
+        );
+    }
+}
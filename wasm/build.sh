@@ -1,4 +1,4 @@
-RUSTFLAGS='-C target-feature=+atomics,+bulk-memory,+mutable-globals' \
+RUSTFLAGS='-C target-feature=+atomics,+bulk-memory,+mutable-globals,+simd128' \
   rustup run nightly-x86_64-unknown-linux-gnu \
   wasm-pack build --target web --out-dir ../pkg . \
   -- -Z build-std=panic_abort,std
\ No newline at end of file